@@ -0,0 +1,153 @@
+use crate::format::{FixedChannels, IoFormat};
+use crate::params::{QualityRecipe, QualitySpec, RuntimeSpec};
+use crate::{Error, Soxr};
+
+/// Tuning for [`AdaptiveResampler`]'s PI controller
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveConfig {
+    /// Buffer fill level the controller aims for, in frames
+    pub target_fill: f64,
+    /// Proportional gain, as relative ratio correction per frame of error
+    pub kp: f64,
+    /// Integral gain, as relative ratio correction per frame of error per
+    /// update
+    pub ki: f64,
+    /// Maximum relative deviation from the nominal ratio, eg. `0.005` for
+    /// +/- 0.5%
+    pub max_deviation: f64,
+    /// Maximum relative change in ratio per update
+    pub max_slew: f64,
+    /// Number of output frames over which each ratio change is slewed;
+    /// typically the number of output frames per update period
+    pub slew_len: usize,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        AdaptiveConfig {
+            target_fill: 0.0,
+            kp: 1e-6,
+            ki: 1e-8,
+            max_deviation: 0.005,
+            max_slew: 1e-4,
+            slew_len: 0,
+        }
+    }
+}
+
+/// Resampler which compensates for drift between two independent clocks,
+/// by steering the io ratio of a variable rate resampler so as to keep a
+/// buffer at its target fill level.
+///
+/// Call [`AdaptiveResampler::update`] once per period with the fill level
+/// of the buffer feeding the resampler. If the resampler instead feeds the
+/// buffer, call [`AdaptiveResampler::update_error`] with
+/// `target_fill - fill_level`.
+pub struct AdaptiveResampler<In: IoFormat, Out: IoFormat = In> {
+    soxr: Soxr<In, Out>,
+    config: AdaptiveConfig,
+    nominal_ratio: f64,
+    ratio: f64,
+    integral: f64,
+}
+
+impl<In, Out> AdaptiveResampler<In, Out>
+    where In: IoFormat, In::Channels: FixedChannels, Out: IoFormat<Channels = In::Channels>
+{
+    /// Create a new variable rate resampler at the given nominal rates
+    pub fn new(input_rate: f64, output_rate: f64, config: AdaptiveConfig)
+        -> Result<Self, Error>
+    {
+        let soxr = Soxr::new_with_params(
+            input_rate,
+            output_rate,
+            QualitySpec::variable_rate(QualityRecipe::default()),
+            RuntimeSpec::default(),
+        )?;
+
+        Ok(Self::with_soxr(soxr, input_rate, output_rate, config))
+    }
+}
+
+impl<In, Out> AdaptiveResampler<In, Out>
+    where In: IoFormat, Out: IoFormat<Channels = In::Channels>
+{
+    /// Wrap an existing resampler, which must have been created with
+    /// variable rate [`QualitySpec`]
+    pub fn with_soxr(
+        soxr: Soxr<In, Out>,
+        input_rate: f64,
+        output_rate: f64,
+        config: AdaptiveConfig,
+    ) -> Self {
+        let nominal_ratio = input_rate / output_rate;
+
+        AdaptiveResampler {
+            soxr,
+            config,
+            nominal_ratio,
+            ratio: nominal_ratio,
+            integral: 0.0,
+        }
+    }
+
+    pub fn soxr(&self) -> &Soxr<In, Out> {
+        &self.soxr
+    }
+
+    pub fn soxr_mut(&mut self) -> &mut Soxr<In, Out> {
+        &mut self.soxr
+    }
+
+    pub fn into_inner(self) -> Soxr<In, Out> {
+        self.soxr
+    }
+
+    pub fn config(&self) -> &AdaptiveConfig {
+        &self.config
+    }
+
+    /// Current io ratio, as last set on the resampler
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    /// Feed the current buffer fill level, in frames, to the controller and
+    /// update the resampler's io ratio. Returns the new io ratio.
+    pub fn update(&mut self, fill_level: f64) -> Result<f64, Error> {
+        self.update_error(fill_level - self.config.target_fill)
+    }
+
+    /// Feed an error term directly to the controller, eg. a timestamp error
+    /// converted to frames. Positive error means the resampler should
+    /// consume input faster. Returns the new io ratio.
+    pub fn update_error(&mut self, error: f64) -> Result<f64, Error> {
+        let max_deviation = self.config.max_deviation;
+
+        // clamp integral term for anti-windup
+        self.integral += self.config.ki * error;
+        self.integral = self.integral.clamp(-max_deviation, max_deviation);
+
+        let correction = self.config.kp * error + self.integral;
+        let correction = correction.clamp(-max_deviation, max_deviation);
+
+        let target = self.nominal_ratio * (1.0 + correction);
+
+        let max_step = self.nominal_ratio * self.config.max_slew;
+        let ratio = self.ratio + (target - self.ratio).clamp(-max_step, max_step);
+
+        self.soxr.set_io_ratio(ratio, self.config.slew_len)?;
+        self.ratio = ratio;
+
+        Ok(ratio)
+    }
+
+    /// Reset the controller state and return the resampler to its nominal
+    /// ratio
+    pub fn reset(&mut self) -> Result<(), Error> {
+        self.soxr.set_io_ratio(self.nominal_ratio, 0)?;
+        self.ratio = self.nominal_ratio;
+        self.integral = 0.0;
+        Ok(())
+    }
+}
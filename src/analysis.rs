@@ -0,0 +1,325 @@
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+use crate::format::Mono;
+use crate::params::QualitySpec;
+use crate::stream::ResamplerStream;
+use crate::{Error, Soxr};
+
+/// Number of input frames in each test signal
+const SIGNAL_LEN: usize = 1 << 16;
+
+/// Number of test frequencies measured across each band
+const GRID_POINTS: usize = 32;
+
+/// Number of bisection steps used to locate the -3 dB point
+const BISECTION_STEPS: usize = 24;
+
+/// Measured frequency response of a resampler configuration. Levels are in
+/// dB relative to the input; frequencies are in Hz.
+#[derive(Debug, Clone, Copy)]
+pub struct Analysis {
+    /// Difference between the largest and smallest gain measured across the
+    /// passband
+    pub passband_ripple_db: f64,
+    /// Frequency at which gain falls 3 dB below the passband gain
+    pub minus_3db_hz: f64,
+    /// Smallest attenuation measured in the stopband, as a positive number
+    /// of dB. Infinite if the stopband lies outside the measurable range.
+    pub stopband_attenuation_db: f64,
+    /// Group delay at DC, in output frames, between an input impulse and
+    /// the centroid of the output response. Near zero for linear phase, as
+    /// libsoxr compensates for the filter's delay.
+    pub group_delay_frames: f64,
+    /// Largest level of aliases landing in the output passband, or `None`
+    /// if none can occur, eg. when upsampling
+    pub aliasing_db: Option<f64>,
+}
+
+/// Measures the response of a resampler built from a [`QualitySpec`] and
+/// rate pair, by resampling impulses and sines
+pub struct Analyzer {
+    stream: ResamplerStream<Mono<f64>>,
+    input_rate: f64,
+    output_rate: f64,
+    passband_end: f64,
+    stopband_begin: f64,
+    input: Vec<f64>,
+    output: Vec<f64>,
+}
+
+impl Analyzer {
+    pub fn new(input_rate: f64, output_rate: f64, quality: QualitySpec) -> Result<Self, Error> {
+        let passband_end = quality.passband_end();
+        let stopband_begin = quality.stopband_begin();
+
+        let soxr = Soxr::builder()
+            .rates(input_rate, output_rate)
+            .quality_spec(quality)
+            .build()?;
+
+        Ok(Analyzer {
+            stream: ResamplerStream::new(soxr),
+            input_rate,
+            output_rate,
+            passband_end,
+            stopband_begin,
+            input: Vec::new(),
+            output: Vec::new(),
+        })
+    }
+
+    /// Nyquist frequency of the lower of the two rates, which the quality
+    /// spec's bandwidth is relative to
+    pub fn nyquist(&self) -> f64 {
+        self.input_rate.min(self.output_rate) / 2.0
+    }
+
+    /// Run all measurements
+    pub fn analyze(&mut self) -> Result<Analysis, Error> {
+        let reference = self.gain_db(self.passband_edge() / GRID_POINTS as f64)?;
+
+        Ok(Analysis {
+            passband_ripple_db: self.passband_ripple_db()?,
+            minus_3db_hz: self.minus_3db_hz(reference)?,
+            stopband_attenuation_db: self.stopband_attenuation_db()?,
+            group_delay_frames: self.group_delay_frames()?,
+            aliasing_db: self.aliasing_db()?,
+        })
+    }
+
+    /// Gain at `frequency`, which must be below both Nyquist frequencies
+    pub fn gain_db(&mut self, frequency: f64) -> Result<f64, Error> {
+        self.tone(frequency)?;
+        Ok(level_db(&self.output, frequency, self.output_rate))
+    }
+
+    /// Resample a unit impulse, returning the output response. The impulse
+    /// is placed midway through the input, at output frame
+    /// `impulse_position()`.
+    pub fn impulse_response(&mut self) -> Result<&[f64], Error> {
+        self.input.clear();
+        self.input.resize(SIGNAL_LEN, 0.0);
+        self.input[SIGNAL_LEN / 2] = 1.0;
+        self.resample()?;
+        Ok(&self.output)
+    }
+
+    /// Position in output frames corresponding to the impulse fed by
+    /// `impulse_response`
+    pub fn impulse_position(&self) -> f64 {
+        (SIGNAL_LEN / 2) as f64 * self.output_rate / self.input_rate
+    }
+
+    fn passband_edge(&self) -> f64 {
+        self.passband_end * self.nyquist()
+    }
+
+    fn stopband_edge(&self) -> f64 {
+        self.stopband_begin * self.nyquist()
+    }
+
+    fn passband_ripple_db(&mut self) -> Result<f64, Error> {
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+
+        for frequency in grid(0.0, self.passband_edge()) {
+            let gain = self.gain_db(frequency)?;
+            min = min.min(gain);
+            max = max.max(gain);
+        }
+
+        Ok(max - min)
+    }
+
+    fn minus_3db_hz(&mut self, reference: f64) -> Result<f64, Error> {
+        let mut low = self.passband_edge();
+        // measurable only below the lower Nyquist frequency
+        let mut high = self.stopband_edge().min(self.nyquist() * (1.0 - 1e-6));
+
+        if self.gain_db(high)? > reference - 3.0 {
+            return Ok(high);
+        }
+
+        for _ in 0..BISECTION_STEPS {
+            let mid = (low + high) / 2.0;
+
+            if self.gain_db(mid)? > reference - 3.0 {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        Ok((low + high) / 2.0)
+    }
+
+    fn stopband_attenuation_db(&mut self) -> Result<f64, Error> {
+        let mut worst = f64::NEG_INFINITY;
+        let stopband = self.stopband_edge();
+
+        if self.input_rate > self.output_rate {
+            // input above the stopband edge aliases down into the output
+            for frequency in grid(stopband, self.input_rate / 2.0) {
+                self.tone(frequency)?;
+                let alias = fold(frequency, self.output_rate);
+                worst = worst.max(level_db(&self.output, alias, self.output_rate));
+            }
+        } else if self.output_rate > self.input_rate {
+            // images of passband input appear in the output stopband
+            for frequency in grid(0.0, self.passband_edge()) {
+                self.tone(frequency)?;
+
+                for image in images(frequency, self.input_rate, self.output_rate) {
+                    if image >= stopband {
+                        worst = worst.max(level_db(&self.output, image, self.output_rate));
+                    }
+                }
+            }
+        }
+
+        Ok(-worst)
+    }
+
+    fn group_delay_frames(&mut self) -> Result<f64, Error> {
+        let position = self.impulse_position();
+        let response = self.impulse_response()?;
+
+        let mut moment = 0.0;
+        let mut sum = 0.0;
+
+        for (index, sample) in response.iter().enumerate() {
+            moment += index as f64 * sample;
+            sum += sample;
+        }
+
+        Ok(moment / sum - position)
+    }
+
+    fn aliasing_db(&mut self) -> Result<Option<f64>, Error> {
+        if self.input_rate <= self.output_rate {
+            return Ok(None);
+        }
+
+        // input frequencies which fold into the output passband
+        let passband = self.passband_edge();
+        let low = self.nyquist().max(self.output_rate - passband);
+        let high = (self.input_rate / 2.0).min(self.output_rate + passband);
+
+        if low >= high {
+            return Ok(None);
+        }
+
+        let mut worst = f64::NEG_INFINITY;
+
+        for frequency in grid(low, high) {
+            self.tone(frequency)?;
+            let alias = fold(frequency, self.output_rate);
+            worst = worst.max(level_db(&self.output, alias, self.output_rate));
+        }
+
+        Ok(Some(worst))
+    }
+
+    /// Resample a unit amplitude sine at `frequency`, leaving its steady
+    /// state portion in `output`
+    fn tone(&mut self, frequency: f64) -> Result<(), Error> {
+        let step = 2.0 * PI * frequency / self.input_rate;
+
+        self.input.clear();
+        self.input.extend((0..SIGNAL_LEN).map(|n| libm::sin(step * n as f64)));
+        self.resample()?;
+
+        // discard filter transients at either end
+        let len = self.output.len();
+        self.output.truncate(len * 3 / 4);
+        self.output.drain(..len / 4);
+
+        Ok(())
+    }
+
+    fn resample(&mut self) -> Result<(), Error> {
+        self.stream.clear()?;
+        self.output.clear();
+        self.stream.push_into(&self.input, &mut self.output)?;
+        self.stream.finish_into(&mut self.output)
+    }
+}
+
+/// Measure the response of a resampler built from `quality` converting
+/// from `input_rate` to `output_rate`
+pub fn analyze(input_rate: f64, output_rate: f64, quality: QualitySpec)
+    -> Result<Analysis, Error>
+{
+    Analyzer::new(input_rate, output_rate, quality)?.analyze()
+}
+
+/// Frequencies spread evenly across `low..high`, avoiding the band edges.
+/// Empty if the band is empty.
+fn grid(low: f64, high: f64) -> impl Iterator<Item = f64> {
+    let points = if high > low { GRID_POINTS } else { 0 };
+    let step = (high - low) / GRID_POINTS as f64;
+    (0..points).map(move |index| low + step * (index as f64 + 0.5))
+}
+
+/// Frequency `frequency` aliases to when sampled at `rate`
+fn fold(frequency: f64, rate: f64) -> f64 {
+    libm::fabs(frequency - rate * libm::round(frequency / rate))
+}
+
+/// Images of `frequency` sampled at `input_rate` which fall below the
+/// Nyquist frequency of `output_rate`
+fn images(frequency: f64, input_rate: f64, output_rate: f64) -> impl Iterator<Item = f64> {
+    let nyquist = output_rate / 2.0;
+
+    (1..)
+        .flat_map(move |k| [k as f64 * input_rate - frequency, k as f64 * input_rate + frequency])
+        .take_while(move |image| *image < nyquist)
+}
+
+/// Level of the component at `frequency` in `signal`, in dB relative to
+/// unit amplitude, estimated with a Hann window
+fn level_db(signal: &[f64], frequency: f64, rate: f64) -> f64 {
+    let step = 2.0 * PI * frequency / rate;
+    let window_step = 2.0 * PI / signal.len() as f64;
+
+    let mut re = 0.0;
+    let mut im = 0.0;
+    let mut window_sum = 0.0;
+
+    for (n, sample) in signal.iter().enumerate() {
+        let n = n as f64;
+        let window = 0.5 - 0.5 * libm::cos(window_step * n);
+        re += window * sample * libm::cos(step * n);
+        im += window * sample * libm::sin(step * n);
+        window_sum += window;
+    }
+
+    let amplitude = 2.0 * libm::sqrt(re * re + im * im) / window_sum;
+    20.0 * libm::log10(amplitude)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::params::QualitySpec;
+
+    use super::analyze;
+
+    #[test]
+    fn default_spec_upsampling() {
+        let quality = QualitySpec::default();
+        let passband_edge = quality.passband_end() * 22050.0;
+        let stopband_edge = quality.stopband_begin() * 22050.0;
+
+        let analysis = analyze(44100.0, 48000.0, quality).unwrap();
+
+        assert!(analysis.passband_ripple_db < 0.1, "{:?}", analysis);
+        assert!(analysis.minus_3db_hz > passband_edge, "{:?}", analysis);
+        assert!(analysis.minus_3db_hz < stopband_edge, "{:?}", analysis);
+        // 20 bit precision gives around 120 dB of rejection
+        assert!(analysis.stopband_attenuation_db > 100.0, "{:?}", analysis);
+        // linear phase, with delay compensated by libsoxr
+        assert!(analysis.group_delay_frames.abs() < 1.0, "{:?}", analysis);
+        assert!(analysis.aliasing_db.is_none(), "{:?}", analysis);
+    }
+}
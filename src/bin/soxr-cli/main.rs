@@ -0,0 +1,425 @@
+//! Command-line resampler for WAV files
+
+use std::env;
+use std::ffi::c_uint;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::process::ExitCode;
+
+use soxr::format::{DynInterleaved, Sample};
+use soxr::params::{
+    Dither, Interpolation, PhaseResponse, QualityFlags, QualityRecipe, QualitySpec, Rolloff,
+};
+use soxr::Soxr;
+
+mod wav;
+
+use wav::{Encoding, Wav};
+
+/// Number of output frames produced per call to `Soxr::process`
+const CHUNK: usize = 4096;
+
+const USAGE: &str = "\
+usage: soxr-cli [options] -r RATE INPUT.wav OUTPUT.wav
+
+options:
+  -r, --rate HZ             output sample rate (required)
+  -e, --encoding ENC        output encoding: s16, s24, s32, f32, f64
+                            (default: same as input)
+  -q, --quality OPTIONS     SoX rate effect options, eg. \"-v -s -I\", or a
+                            preset: vhq, hq, mq, lq, qq. Replaces recipe,
+                            rolloff and flags.
+      --recipe RECIPE       quick, low, medium, 16, 20, 24, 28, 32
+                            (default: 20)
+      --rolloff ROLLOFF     small, medium, none (default: small)
+      --hi-prec-clock       increase irrational ratio accuracy
+      --double-precision    use double precision even if precision <= 20
+      --variable-rate       use a variable rate resampler
+      --phase PHASE         minimum, intermediate, linear, maximum, or
+                            0 to 100
+      --precision BITS      conversion precision
+      --bandwidth PERCENT   passband end, as percent of Nyquist
+      --stopband PERCENT    stopband begin, as percent of Nyquist
+      --threads N           number of threads, 0 for automatic
+      --interpolation INTERP
+                            coefficient interpolation: auto, low, high
+      --dither DITHER       dither for integer output: tpdf, none
+                            (default: tpdf)
+      --gain DB             gain to apply during conversion
+  -h, --help                show this message
+";
+
+struct Options {
+    input: String,
+    output: String,
+    rate: f64,
+    encoding: Option<Encoding>,
+    quality: Option<QualitySpec>,
+    recipe: QualityRecipe,
+    rolloff: Rolloff,
+    flags: QualityFlags,
+    phase: Option<PhaseResponse>,
+    precision: Option<f64>,
+    passband_end: Option<f64>,
+    stopband_begin: Option<f64>,
+    threads: Option<c_uint>,
+    interpolation: Option<Interpolation>,
+    dither: Dither,
+    gain_db: f64,
+}
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let result = parse_args(&args).and_then(|options| match options {
+        Some(options) => run(&options),
+        None => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+    });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("soxr-cli: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Parse command line arguments, returning `None` if help was requested
+fn parse_args(args: &[String]) -> Result<Option<Options>, String> {
+    let mut options = Options {
+        input: String::new(),
+        output: String::new(),
+        rate: 0.0,
+        encoding: None,
+        quality: None,
+        recipe: QualityRecipe::default(),
+        rolloff: Rolloff::default(),
+        flags: QualityFlags::default(),
+        phase: None,
+        precision: None,
+        passband_end: None,
+        stopband_begin: None,
+        threads: None,
+        interpolation: None,
+        dither: Dither::default(),
+        gain_db: 0.0,
+    };
+
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().ok_or_else(|| format!("{} requires a value", arg))
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-r" | "--rate" => options.rate = parse_number(arg, value()?)?,
+            "-e" | "--encoding" => {
+                let name = value()?;
+                let encoding = Encoding::parse(name)
+                    .ok_or_else(|| format!("unknown encoding: {}", name))?;
+                options.encoding = Some(encoding);
+            }
+            "-q" | "--quality" => {
+                let quality = value()?.parse::<QualitySpec>()
+                    .map_err(|e| format!("{}: {}", arg, e))?;
+                options.quality = Some(quality);
+            }
+            "--recipe" => options.recipe = parse_recipe(value()?)?,
+            "--rolloff" => options.rolloff = parse_rolloff(value()?)?,
+            "--hi-prec-clock" => options.flags |= QualityFlags::HighPrecisionClock,
+            "--double-precision" => options.flags |= QualityFlags::DoublePrecision,
+            "--variable-rate" => options.flags |= QualityFlags::VariableRate,
+            "--phase" => options.phase = Some(parse_phase(value()?)?),
+            "--precision" => options.precision = Some(parse_number(arg, value()?)?),
+            "--bandwidth" => options.passband_end = Some(parse_number(arg, value()?)? / 100.0),
+            "--stopband" => options.stopband_begin = Some(parse_number(arg, value()?)? / 100.0),
+            "--threads" => {
+                let threads = value()?;
+                let threads = threads.parse()
+                    .map_err(|_| format!("{}: invalid thread count: {}", arg, threads))?;
+                options.threads = Some(threads);
+            }
+            "--interpolation" => options.interpolation = Some(parse_interpolation(value()?)?),
+            "--dither" => options.dither = parse_dither(value()?)?,
+            "--gain" => options.gain_db = parse_number(arg, value()?)?,
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("unknown option: {}", arg));
+            }
+            _ => paths.push(arg.clone()),
+        }
+    }
+
+    let [input, output] = <[String; 2]>::try_from(paths)
+        .map_err(|_| "expected input and output paths, see --help".to_string())?;
+
+    if options.rate <= 0.0 {
+        return Err("output rate is required, see --help".to_string());
+    }
+
+    options.input = input;
+    options.output = output;
+    Ok(Some(options))
+}
+
+fn parse_number(option: &str, value: &str) -> Result<f64, String> {
+    value.parse().map_err(|_| format!("{}: invalid number: {}", option, value))
+}
+
+fn parse_recipe(name: &str) -> Result<QualityRecipe, String> {
+    match name {
+        "quick" => Ok(QualityRecipe::Quick),
+        "low" => Ok(QualityRecipe::Low),
+        "medium" => Ok(QualityRecipe::Medium),
+        "16" => Ok(QualityRecipe::Bits16),
+        "20" | "high" => Ok(QualityRecipe::Bits20),
+        "24" => Ok(QualityRecipe::Bits24),
+        "28" | "very-high" => Ok(QualityRecipe::Bits28),
+        "32" => Ok(QualityRecipe::Bits32),
+        _ => Err(format!("unknown recipe: {}", name)),
+    }
+}
+
+fn parse_rolloff(name: &str) -> Result<Rolloff, String> {
+    match name {
+        "small" => Ok(Rolloff::Small),
+        "medium" => Ok(Rolloff::Medium),
+        "none" => Ok(Rolloff::None),
+        _ => Err(format!("unknown rolloff: {}", name)),
+    }
+}
+
+fn parse_phase(name: &str) -> Result<PhaseResponse, String> {
+    match name {
+        "minimum" => Ok(PhaseResponse::MINIMUM),
+        "intermediate" => Ok(PhaseResponse::INTERMEDIATE),
+        "linear" => Ok(PhaseResponse::LINEAR),
+        "maximum" => Ok(PhaseResponse::MAXIMUM),
+        _ => {
+            let value = parse_number("--phase", name)?;
+            PhaseResponse::new(value).map_err(|e| format!("--phase: {}", e))
+        }
+    }
+}
+
+fn parse_interpolation(name: &str) -> Result<Interpolation, String> {
+    match name {
+        "auto" => Ok(Interpolation::Auto),
+        "low" => Ok(Interpolation::Low),
+        "high" => Ok(Interpolation::High),
+        _ => Err(format!("unknown interpolation: {}", name)),
+    }
+}
+
+fn parse_dither(name: &str) -> Result<Dither, String> {
+    match name {
+        "tpdf" => Ok(Dither::Tpdf),
+        "none" => Ok(Dither::None),
+        _ => Err(format!("unknown dither: {}", name)),
+    }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let file = File::open(&options.input)
+        .map_err(|e| format!("{}: {}", options.input, e))?;
+    let input = wav::read(BufReader::new(file))
+        .map_err(|e| format!("{}: {}", options.input, e))?;
+
+    let encoding = options.encoding.unwrap_or(input.encoding);
+
+    // 16 and 32 bit output is produced by libsoxr, which applies dither and
+    // counts clipped samples itself
+    let samples = match encoding {
+        Encoding::Int16 => resample::<i16>(options, &input, 32768.0)?,
+        Encoding::Int24 => {
+            // libsoxr has no 24 bit format, so dither and clip here
+            let mut samples = resample::<f64>(options, &input, 1.0)?;
+
+            if options.dither == Dither::Tpdf {
+                dither(&mut samples, 24);
+            }
+
+            warn_clips(clips(&samples, 24));
+            samples
+        }
+        Encoding::Int32 => resample::<i32>(options, &input, 2147483648.0)?,
+        Encoding::Float32 => resample::<f32>(options, &input, 1.0)?,
+        Encoding::Float64 => resample::<f64>(options, &input, 1.0)?,
+    };
+
+    let output = Wav {
+        channels: input.channels,
+        sample_rate: options.rate as u32,
+        encoding,
+        samples,
+    };
+
+    let file = File::create(&options.output)
+        .map_err(|e| format!("{}: {}", options.output, e))?;
+    wav::write(BufWriter::new(file), &output)
+        .map_err(|e| format!("{}: {}", options.output, e))
+}
+
+/// Resample interleaved input to samples of type `S`, returning them
+/// normalised by `full_scale`
+fn resample<S: Sample>(options: &Options, input: &Wav, full_scale: f64)
+    -> Result<Vec<f64>, String>
+{
+    let channels = input.channels;
+
+    let mut builder = Soxr::<DynInterleaved<f64>, DynInterleaved<S>>::dynamic_builder(channels)
+        .rates(input.sample_rate as f64, options.rate)
+        .recipe(options.recipe)
+        .rolloff(options.rolloff)
+        .flags(options.flags)
+        .dither(options.dither)
+        .scale(10f64.powf(options.gain_db / 20.0));
+
+    if let Some(quality) = &options.quality {
+        builder = builder.quality_spec(quality.clone());
+    }
+
+    if let Some(phase) = options.phase {
+        builder = builder.phase(phase);
+    }
+
+    if let Some(precision) = options.precision {
+        builder = builder.precision(precision);
+    }
+
+    if let Some(passband_end) = options.passband_end {
+        builder = builder.passband_end(passband_end);
+    }
+
+    if let Some(stopband_begin) = options.stopband_begin {
+        builder = builder.stopband_begin(stopband_begin);
+    }
+
+    if let Some(threads) = options.threads {
+        builder = builder.num_threads(threads);
+    }
+
+    if let Some(interpolation) = options.interpolation {
+        builder = builder.interpolation(interpolation);
+    }
+
+    let mut soxr = builder.build().map_err(|e| e.to_string())?;
+
+    let mut output = Vec::new();
+    let mut buffer = vec![S::from_f64(0.0); CHUNK * channels];
+    let mut consumed = 0;
+
+    while consumed < input.samples.len() {
+        let processed = soxr.process(&input.samples[consumed..], &mut buffer)
+            .map_err(|e| e.to_string())?;
+
+        // libsoxr always accepts some input when given room for output, so
+        // no progress means the rest of the input would be lost
+        if processed.input_frames == 0 && processed.output_frames == 0 {
+            return Err("resampler stopped accepting input".to_string());
+        }
+
+        consumed += processed.input_frames * channels;
+        output.extend_from_slice(&buffer[..processed.output_frames * channels]);
+    }
+
+    loop {
+        let produced = soxr.drain(&mut buffer).map_err(|e| e.to_string())?;
+
+        if produced == 0 {
+            break;
+        }
+
+        output.extend_from_slice(&buffer[..produced * channels]);
+    }
+
+    warn_clips(soxr.num_clips());
+
+    Ok(output.into_iter().map(|sample| sample.to_f64() / full_scale).collect())
+}
+
+/// Add TPDF dither of one least significant bit at `bits` resolution to
+/// samples normalised to +/- 1.0
+fn dither(samples: &mut [f64], bits: u32) {
+    let lsb = 1.0 / (1u64 << (bits - 1)) as f64;
+
+    // xorshift64 is plenty for dither noise
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut uniform = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 11) as f64 / (1u64 << 53) as f64
+    };
+
+    for sample in samples {
+        *sample += (uniform() - uniform()) * lsb;
+    }
+}
+
+/// Number of samples normalised to +/- 1.0 which clip when rounded to
+/// `bits` bit integers
+fn clips(samples: &[f64], bits: u32) -> usize {
+    let full_scale = (1u64 << (bits - 1)) as f64;
+
+    samples.iter()
+        .map(|sample| (sample * full_scale).round())
+        .filter(|value| *value < -full_scale || *value > full_scale - 1.0)
+        .count()
+}
+
+fn warn_clips(clips: usize) {
+    if clips > 0 {
+        eprintln!("soxr-cli: warning: {} samples clipped", clips);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clips, dither, parse_args};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn help() {
+        assert!(matches!(parse_args(&args(&["--help"])), Ok(None)));
+        assert!(matches!(parse_args(&args(&["-r", "48000", "-h"])), Ok(None)));
+    }
+
+    #[test]
+    fn help_as_option_value() {
+        // -h is the SoX high quality option here, not a request for help
+        let options = parse_args(&args(&["-q", "-h", "-r", "48000", "in.wav", "out.wav"]))
+            .unwrap()
+            .unwrap();
+
+        assert!(options.quality.is_some());
+        assert_eq!(options.input, "in.wav");
+    }
+
+    #[test]
+    fn dither_within_one_lsb() {
+        let lsb = 1.0 / 8388608.0;
+        let mut samples = vec![0.25; 10000];
+        dither(&mut samples, 24);
+
+        assert!(samples.iter().all(|sample| (sample - 0.25).abs() < lsb));
+        assert!(samples.iter().any(|sample| *sample != 0.25));
+
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        assert!((mean - 0.25).abs() < lsb / 10.0);
+    }
+
+    #[test]
+    fn clip_count() {
+        assert_eq!(clips(&[0.0, 0.5, -1.0, 1.0, -1.5], 24), 2);
+    }
+}
@@ -0,0 +1,233 @@
+use std::io::{self, Read, Write};
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// Sample encoding of a WAV file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Int16,
+    Int24,
+    Int32,
+    Float32,
+    Float64,
+}
+
+impl Encoding {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "s16" => Some(Encoding::Int16),
+            "s24" => Some(Encoding::Int24),
+            "s32" => Some(Encoding::Int32),
+            "f32" => Some(Encoding::Float32),
+            "f64" => Some(Encoding::Float64),
+            _ => None,
+        }
+    }
+
+    fn from_format(tag: u16, bits: u16) -> Option<Self> {
+        match (tag, bits) {
+            (FORMAT_PCM, 16) => Some(Encoding::Int16),
+            (FORMAT_PCM, 24) => Some(Encoding::Int24),
+            (FORMAT_PCM, 32) => Some(Encoding::Int32),
+            (FORMAT_FLOAT, 32) => Some(Encoding::Float32),
+            (FORMAT_FLOAT, 64) => Some(Encoding::Float64),
+            _ => None,
+        }
+    }
+
+    fn tag(self) -> u16 {
+        match self {
+            Encoding::Int16 | Encoding::Int24 | Encoding::Int32 => FORMAT_PCM,
+            Encoding::Float32 | Encoding::Float64 => FORMAT_FLOAT,
+        }
+    }
+
+    pub fn bytes(self) -> usize {
+        match self {
+            Encoding::Int16 => 2,
+            Encoding::Int24 => 3,
+            Encoding::Int32 | Encoding::Float32 => 4,
+            Encoding::Float64 => 8,
+        }
+    }
+}
+
+/// Decoded WAV file, with samples interleaved and normalised to +/- 1.0
+pub struct Wav {
+    pub channels: usize,
+    pub sample_rate: u32,
+    pub encoding: Encoding,
+    pub samples: Vec<f64>,
+}
+
+pub fn read(mut reader: impl Read) -> io::Result<Wav> {
+    let mut header = [0; 12];
+    reader.read_exact(&mut header)?;
+
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Err(invalid("not a RIFF WAVE file"));
+    }
+
+    let mut format = None;
+
+    loop {
+        let mut chunk = [0; 8];
+        reader.read_exact(&mut chunk)?;
+
+        let id = &chunk[0..4];
+        let len = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as usize;
+
+        if id == b"fmt " {
+            let mut body = vec![0; len];
+            reader.read_exact(&mut body)?;
+            skip_padding(&mut reader, len)?;
+            format = Some(parse_format(&body)?);
+        } else if id == b"data" {
+            let (channels, sample_rate, encoding) = format
+                .ok_or_else(|| invalid("data chunk before fmt chunk"))?;
+
+            let mut data = vec![0; len];
+            reader.read_exact(&mut data)?;
+
+            // any trailing partial frame is dropped
+            let mut samples = decode(&data, encoding);
+            samples.truncate(samples.len() / channels * channels);
+
+            return Ok(Wav {
+                channels,
+                sample_rate,
+                encoding,
+                samples,
+            });
+        } else {
+            // chunks are padded to an even length
+            io::copy(&mut (&mut reader).take((len + len % 2) as u64), &mut io::sink())?;
+        }
+    }
+}
+
+pub fn write(mut writer: impl Write, wav: &Wav) -> io::Result<()> {
+    let data = encode(&wav.samples, wav.encoding);
+
+    let block_align = wav.channels * wav.encoding.bytes();
+    let byte_rate = wav.sample_rate as usize * block_align;
+
+    let data_len = u32::try_from(data.len())
+        .map_err(|_| invalid("output too large for WAV"))?;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_len + data_len % 2).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&wav.encoding.tag().to_le_bytes())?;
+    writer.write_all(&(wav.channels as u16).to_le_bytes())?;
+    writer.write_all(&wav.sample_rate.to_le_bytes())?;
+    writer.write_all(&(byte_rate as u32).to_le_bytes())?;
+    writer.write_all(&(block_align as u16).to_le_bytes())?;
+    writer.write_all(&(wav.encoding.bytes() as u16 * 8).to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_len.to_le_bytes())?;
+    writer.write_all(&data)?;
+
+    if !data.len().is_multiple_of(2) {
+        writer.write_all(&[0])?;
+    }
+
+    writer.flush()
+}
+
+fn parse_format(body: &[u8]) -> io::Result<(usize, u32, Encoding)> {
+    if body.len() < 16 {
+        return Err(invalid("fmt chunk too short"));
+    }
+
+    let u16_at = |offset: usize| u16::from_le_bytes([body[offset], body[offset + 1]]);
+
+    let mut tag = u16_at(0);
+    let channels = u16_at(2) as usize;
+    let sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+    let bits = u16_at(14);
+
+    if tag == FORMAT_EXTENSIBLE {
+        // format tag is the first two bytes of the sub-format GUID
+        if body.len() < 26 {
+            return Err(invalid("extensible fmt chunk too short"));
+        }
+
+        tag = u16_at(24);
+    }
+
+    if channels == 0 {
+        return Err(invalid("WAV file has no channels"));
+    }
+
+    let encoding = Encoding::from_format(tag, bits)
+        .ok_or_else(|| invalid("unsupported WAV sample format"))?;
+
+    Ok((channels, sample_rate, encoding))
+}
+
+fn skip_padding(reader: &mut impl Read, len: usize) -> io::Result<()> {
+    if !len.is_multiple_of(2) {
+        reader.read_exact(&mut [0])?;
+    }
+
+    Ok(())
+}
+
+fn decode(data: &[u8], encoding: Encoding) -> Vec<f64> {
+    let chunks = data.chunks_exact(encoding.bytes());
+
+    match encoding {
+        Encoding::Int16 => chunks
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f64 / 32768.0)
+            .collect(),
+        Encoding::Int24 => chunks
+            // place in the top 24 bits of an i32 to sign extend
+            .map(|b| i32::from_le_bytes([0, b[0], b[1], b[2]]) as f64 / 2147483648.0)
+            .collect(),
+        Encoding::Int32 => chunks
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64 / 2147483648.0)
+            .collect(),
+        Encoding::Float32 => chunks
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+            .collect(),
+        Encoding::Float64 => chunks
+            .map(|b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+            .collect(),
+    }
+}
+
+fn encode(samples: &[f64], encoding: Encoding) -> Vec<u8> {
+    let mut data = Vec::with_capacity(samples.len() * encoding.bytes());
+
+    for &sample in samples {
+        match encoding {
+            Encoding::Int16 => {
+                let value = (sample * 32768.0).round() as i16;
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            Encoding::Int24 => {
+                let value = (sample * 8388608.0).round().clamp(-8388608.0, 8388607.0) as i32;
+                data.extend_from_slice(&value.to_le_bytes()[..3]);
+            }
+            Encoding::Int32 => {
+                let value = (sample * 2147483648.0).round() as i32;
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            Encoding::Float32 => data.extend_from_slice(&(sample as f32).to_le_bytes()),
+            Encoding::Float64 => data.extend_from_slice(&sample.to_le_bytes()),
+        }
+    }
+
+    data
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -1,6 +1,7 @@
 use core::array;
 use core::ffi::c_void;
 use core::marker::PhantomData;
+use core::ptr::{null, null_mut};
 
 use crate::format::Sample;
 
@@ -89,3 +90,110 @@ impl<'a, S: Sample, const CHANNELS: usize> PlanarMut<'a, S, CHANNELS> {
         self.planes.as_mut_ptr().cast()
     }
 }
+
+/// Maximum number of planes in a [`DynPlanarBuf`] or [`DynPlanarMut`]
+pub const MAX_DYN_PLANES: usize = 32;
+
+pub struct DynPlanarBuf<'a, S: Sample> {
+    frames: usize,
+    channels: usize,
+    planes: [*const S; MAX_DYN_PLANES],
+    _phantom: PhantomData<&'a [S]>,
+}
+
+impl<'a, S: Sample> DynPlanarBuf<'a, S> {
+    /// Create new `DynPlanarBuf` from slice of plane slices
+    ///
+    /// # Panics
+    ///
+    /// Panics if all plane slices are not of same length, or if there are
+    /// more than [`MAX_DYN_PLANES`] planes
+    pub fn new(planes: &[&'a [S]]) -> Self {
+        let channels = planes.len();
+        if channels > MAX_DYN_PLANES {
+            panic!("too many planes: len={channels}, max={MAX_DYN_PLANES}");
+        }
+
+        let frames = planes.first().map(|plane| plane.len()).unwrap_or(0);
+
+        let planes = array::from_fn(|index| {
+            let Some(plane) = planes.get(index) else {
+                return null();
+            };
+
+            // validate plane length
+            let length = plane.len();
+            if length != frames {
+                panic!("plane at index {index} of different length to previous planes: len={length}, expected={frames}");
+            }
+
+            plane.as_ptr()
+        });
+
+        DynPlanarBuf { frames, channels, planes, _phantom: PhantomData }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn as_ptr(&self) -> *const c_void {
+        self.planes.as_ptr().cast()
+    }
+}
+
+pub struct DynPlanarMut<'a, S: Sample> {
+    frames: usize,
+    channels: usize,
+    planes: [*mut S; MAX_DYN_PLANES],
+    _phantom: PhantomData<&'a mut [S]>,
+}
+
+impl<'a, S: Sample> DynPlanarMut<'a, S> {
+    /// Create new `DynPlanarMut` from slice of plane slices
+    ///
+    /// # Panics
+    ///
+    /// Panics if all plane slices are not of same length, or if there are
+    /// more than [`MAX_DYN_PLANES`] planes
+    pub fn new(planes: &'a mut [&'a mut [S]]) -> Self {
+        let channels = planes.len();
+        if channels > MAX_DYN_PLANES {
+            panic!("too many planes: len={channels}, max={MAX_DYN_PLANES}");
+        }
+
+        let frames = planes.first().map(|plane| plane.len()).unwrap_or(0);
+
+        let planes = array::from_fn(|index| {
+            let Some(plane) = planes.get_mut(index) else {
+                return null_mut();
+            };
+
+            // validate plane length
+            let length = plane.len();
+            if length != frames {
+                panic!("plane at index {index} of different length to previous planes: len={length}, expected={frames}");
+            }
+
+            plane.as_mut_ptr()
+        });
+
+        DynPlanarMut { frames, channels, planes, _phantom: PhantomData }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn as_ptr(&mut self) -> *mut c_void {
+        self.planes.as_mut_ptr().cast()
+    }
+}
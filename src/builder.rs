@@ -0,0 +1,233 @@
+use core::ffi::c_uint;
+use core::marker::PhantomData;
+
+use crate::error::{self, Error};
+use crate::format::IoFormat;
+use crate::params::{
+    Dither, Interpolation, IoSpec, PhaseResponse, QualityFlags, QualityRecipe,
+    QualitySpec, Rolloff, RuntimeSpec,
+};
+use crate::{ChannelCount, Soxr};
+
+/// Builder for [`Soxr`], collecting rates and quality, runtime and IO
+/// parameters in one place. Created with [`Soxr::builder`] or
+/// [`Soxr::dynamic_builder`].
+pub struct SoxrBuilder<In: IoFormat, Out: IoFormat = In> {
+    input_rate: f64,
+    output_rate: f64,
+    channels: ChannelCount,
+    /// Base quality spec, replacing recipe, rolloff and flags if set
+    quality: Option<QualitySpec>,
+    recipe: QualityRecipe,
+    rolloff: Rolloff,
+    flags: QualityFlags,
+    precision: Option<f64>,
+    phase: Option<PhaseResponse>,
+    passband_end: Option<f64>,
+    stopband_begin: Option<f64>,
+    runtime: RuntimeSpec,
+    io: IoSpec,
+    _phantom: PhantomData<(In, Out)>,
+}
+
+impl<In, Out> SoxrBuilder<In, Out>
+    where In: IoFormat, Out: IoFormat<Channels = In::Channels>
+{
+    pub(crate) fn new(channels: ChannelCount) -> Self {
+        SoxrBuilder {
+            input_rate: 0.0,
+            output_rate: 0.0,
+            channels,
+            quality: None,
+            recipe: QualityRecipe::default(),
+            rolloff: Rolloff::default(),
+            flags: QualityFlags::default(),
+            precision: None,
+            phase: None,
+            passband_end: None,
+            stopband_begin: None,
+            runtime: RuntimeSpec::default(),
+            io: IoSpec::default(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Input and output sample rates. Both default to zero and must be set
+    /// before building.
+    pub fn rates(mut self, input_rate: f64, output_rate: f64) -> Self {
+        self.input_rate = input_rate;
+        self.output_rate = output_rate;
+        self
+    }
+
+    pub fn input_rate(mut self, input_rate: f64) -> Self {
+        self.input_rate = input_rate;
+        self
+    }
+
+    pub fn output_rate(mut self, output_rate: f64) -> Self {
+        self.output_rate = output_rate;
+        self
+    }
+
+
+    /// Quality recipe; typically [`QualityRecipe::high`]
+    pub fn recipe(mut self, recipe: QualityRecipe) -> Self {
+        self.recipe = recipe;
+        self
+    }
+
+    pub fn rolloff(mut self, rolloff: Rolloff) -> Self {
+        self.rolloff = rolloff;
+        self
+    }
+
+    pub fn flags(mut self, flags: QualityFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Shorthand for setting [`QualityFlags::VariableRate`]
+    pub fn variable_rate(mut self) -> Self {
+        self.flags |= QualityFlags::VariableRate;
+        self
+    }
+
+    /// Conversion precision, in bits, overriding the recipe
+    pub fn precision(mut self, precision: f64) -> Self {
+        self.precision = Some(precision);
+        self
+    }
+
+    /// Phase response, overriding the recipe
+    pub fn phase(mut self, phase: PhaseResponse) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+
+    /// 0dB pt. bandwidth to preserve, overriding the recipe; nyquist=1
+    pub fn passband_end(mut self, passband_end: f64) -> Self {
+        self.passband_end = Some(passband_end);
+        self
+    }
+
+    /// Aliasing/imaging control, overriding the recipe; > passband_end
+    pub fn stopband_begin(mut self, stopband_begin: f64) -> Self {
+        self.stopband_begin = Some(stopband_begin);
+        self
+    }
+
+    /// Use `quality` as the base quality spec, in place of the recipe,
+    /// rolloff and flags. Precision, phase and bandwidth set on the builder
+    /// still override it.
+    pub fn quality_spec(mut self, quality: QualitySpec) -> Self {
+        self.quality = Some(quality);
+        self
+    }
+
+
+    /// 0: per OMP_NUM_THREADS; 1: 1 thread; typically 1
+    pub fn num_threads(mut self, num_threads: c_uint) -> Self {
+        self.runtime.set_num_threads(num_threads);
+        self
+    }
+
+    /// For DFT efficiency. \[8,15\]; typically 10
+    pub fn log2_min_dft_size(mut self, log2_min_dft_size: c_uint) -> Self {
+        self.runtime.set_log2_min_dft_size(log2_min_dft_size);
+        self
+    }
+
+    /// For DFT efficiency. \[8,20\]; typically 17
+    pub fn log2_large_dft_size(mut self, log2_large_dft_size: c_uint) -> Self {
+        self.runtime.set_log2_large_dft_size(log2_large_dft_size);
+        self
+    }
+
+    /// For `Interpolation::Auto`, typically 400
+    pub fn coef_size_kbytes(mut self, coef_size_kbytes: c_uint) -> Self {
+        self.runtime.set_coef_size_kbytes(coef_size_kbytes);
+        self
+    }
+
+    pub fn interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.runtime.set_interpolation(interpolation);
+        self
+    }
+
+    /// Replace all runtime parameters set so far
+    pub fn runtime_spec(mut self, runtime: RuntimeSpec) -> Self {
+        self.runtime = runtime;
+        self
+    }
+
+
+    /// Linear gain to apply during conversion; typically 1.0
+    pub fn scale(mut self, scale: f64) -> Self {
+        self.io.set_scale(scale);
+        self
+    }
+
+    pub fn dither(mut self, dither: Dither) -> Self {
+        self.io.set_dither(dither);
+        self
+    }
+
+    /// Replace all IO parameters set so far
+    pub fn io_spec(mut self, io: IoSpec) -> Self {
+        self.io = io;
+        self
+    }
+
+
+    /// Quality spec the resampler will be created with
+    pub fn to_quality_spec(&self) -> QualitySpec {
+        let mut quality = match &self.quality {
+            Some(quality) => quality.clone(),
+            None => QualitySpec::configure(self.recipe, self.rolloff, self.flags),
+        };
+
+        if let Some(precision) = self.precision {
+            quality.set_precision(precision);
+        }
+
+        if let Some(phase) = self.phase {
+            quality.set_phase(phase);
+        }
+
+        if let Some(passband_end) = self.passband_end {
+            quality.set_passband_end(passband_end);
+        }
+
+        if let Some(stopband_begin) = self.stopband_begin {
+            quality.set_stopband_begin(stopband_begin);
+        }
+
+        quality
+    }
+
+    /// Create the resampler. Returns [`Error::InvalidRate`] if either rate
+    /// has not been set, or [`Error::InvalidChannelCount`] if the channel
+    /// count is zero.
+    pub fn build(self) -> Result<Soxr<In, Out>, Error> {
+        // written so that NaN rates are rejected too
+        if !(self.input_rate > 0.0 && self.output_rate > 0.0) {
+            return Err(error::RATE_NOT_SET);
+        }
+
+        if self.channels == 0 {
+            return Err(error::NO_CHANNELS);
+        }
+
+        let quality = self.to_quality_spec();
+
+        Soxr::create(
+            self.input_rate,
+            self.output_rate,
+            self.channels,
+            quality,
+            self.runtime,
+            self.io,
+        )
+    }
+}
@@ -3,15 +3,100 @@ use core::fmt::{Display, Debug};
 use core::ptr::null_mut;
 use libsoxr_sys as sys;
 
-pub struct Error(&'static CStr);
+/// Error returned by libsoxr or by this crate, categorised by cause
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Input or output rate, or io ratio, is invalid or out of range
+    InvalidRate(&'static CStr),
+    /// Channel count is invalid, or does not match the resampler
+    InvalidChannelCount(&'static CStr),
+    /// Memory allocation failed
+    Allocation(&'static CStr),
+    /// Quality, runtime or IO parameters are invalid
+    InvalidParameter(&'static CStr),
+    /// Call made while the resampler is in the wrong state, eg. `process`
+    /// after `drain`
+    InvalidState(&'static CStr),
+    /// Any other error reported by libsoxr
+    Soxr(&'static CStr),
+}
+
+const fn message(bytes: &'static [u8]) -> &'static CStr {
+    match CStr::from_bytes_with_nul(bytes) {
+        Ok(message) => message,
+        Err(_) => panic!("error message must be nul terminated"),
+    }
+}
+
+pub(crate) const RATE_NOT_SET: Error = Error::InvalidRate(
+    message(b"input and output rates must be greater than 0\0")
+);
+
+pub(crate) const CHANNEL_COUNT_TOO_LARGE: Error = Error::InvalidChannelCount(
+    message(b"channel count does not fit in c_uint\0")
+);
+
+pub(crate) const NO_CHANNELS: Error = Error::InvalidChannelCount(
+    message(b"channel count must be at least 1\0")
+);
+
+pub(crate) const PROCESS_AFTER_DRAIN: Error = Error::InvalidState(
+    message(b"process called after drain without clear\0")
+);
+
+pub(crate) const TOO_MANY_DISCONTINUITIES: Error = Error::InvalidState(
+    message(b"too many discontinuities pending in the resampler\0")
+);
+
+pub(crate) const INVALID_PRECISION: Error = Error::InvalidParameter(
+    message(b"precision must be between 0 and 33 bits\0")
+);
+
+pub(crate) const INVALID_PHASE_RESPONSE: Error = Error::InvalidParameter(
+    message(b"phase response must be between 0 and 100\0")
+);
+
+pub(crate) const INVALID_PASSBAND: Error = Error::InvalidParameter(
+    message(b"passband end must be greater than 0 and at most 1\0")
+);
 
-pub(crate) const CHANNEL_COUNT_TOO_LARGE: Error = Error(
-    unsafe { CStr::from_bytes_with_nul_unchecked(b"channel count does not fit in c_uint\0") }
+pub(crate) const PASSBAND_NOT_BELOW_STOPBAND: Error = Error::InvalidParameter(
+    message(b"passband end must be below stopband begin\0")
+);
+
+pub(crate) const INVALID_QUALITY_OPTION: Error = Error::InvalidParameter(
+    message(b"unrecognised quality option\0")
+);
+
+pub(crate) const MISSING_QUALITY_VALUE: Error = Error::InvalidParameter(
+    message(b"quality option requires a value\0")
+);
+
+pub(crate) const INVALID_QUALITY_VALUE: Error = Error::InvalidParameter(
+    message(b"quality option value is not a number\0")
 );
 
 impl Error {
+    /// Categorise a raw libsoxr error message. libsoxr reports errors as
+    /// a fixed set of messages; any not recognised are [`Error::Soxr`].
     pub unsafe fn from_raw(error: sys::soxr_error_t) -> Self {
-        Error(CStr::from_ptr(error))
+        let message = CStr::from_ptr(error);
+
+        match message.to_bytes() {
+            b"malloc failed" => Error::Allocation(message),
+            b"invalid # of channels" => Error::InvalidChannelCount(message),
+            b"I/O ratio out-of-range" => Error::InvalidRate(message),
+            b"invalid io datatype(s)"
+            | b"invalid quality type" => Error::InvalidParameter(message),
+            b"# of channels can't be changed"
+            | b"must set # channels before O/P rate"
+            | b"variable-rate resampling not enabled"
+            | b"varying O/P rate is not supported with this configuration" => {
+                Error::InvalidState(message)
+            }
+            _ => Error::Soxr(message),
+        }
     }
 
     pub(crate) unsafe fn check(error: sys::soxr_error_t) -> Result<(), Error> {
@@ -23,12 +108,30 @@ impl Error {
     }
 
     pub fn as_cstr(&self) -> &'static CStr {
-        self.0
+        match *self {
+            Error::InvalidRate(message)
+            | Error::InvalidChannelCount(message)
+            | Error::Allocation(message)
+            | Error::InvalidParameter(message)
+            | Error::InvalidState(message)
+            | Error::Soxr(message) => message,
+        }
     }
 
     pub fn as_str(&self) -> &'static str {
         // SAFETY: all soxr error strings are valid utf-8
-        unsafe { core::str::from_utf8_unchecked(self.0.to_bytes()) }
+        unsafe { core::str::from_utf8_unchecked(self.as_cstr().to_bytes()) }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Error::InvalidRate(_) => "InvalidRate",
+            Error::InvalidChannelCount(_) => "InvalidChannelCount",
+            Error::Allocation(_) => "Allocation",
+            Error::InvalidParameter(_) => "InvalidParameter",
+            Error::InvalidState(_) => "InvalidState",
+            Error::Soxr(_) => "Soxr",
+        }
     }
 }
 
@@ -40,6 +143,28 @@ impl Display for Error {
 
 impl Debug for Error {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_tuple("Error").field(&self.as_str()).finish()
+        f.debug_tuple(self.name()).field(&self.as_str()).finish()
+    }
+}
+
+impl core::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+
+    fn category(message: &'static core::ffi::CStr) -> &'static str {
+        unsafe { Error::from_raw(message.as_ptr()).name() }
+    }
+
+    #[test]
+    fn from_raw() {
+        assert_eq!(category(c"malloc failed"), "Allocation");
+        assert_eq!(category(c"invalid # of channels"), "InvalidChannelCount");
+        assert_eq!(category(c"I/O ratio out-of-range"), "InvalidRate");
+        assert_eq!(category(c"invalid quality type"), "InvalidParameter");
+        assert_eq!(category(c"variable-rate resampling not enabled"), "InvalidState");
+        assert_eq!(category(c"must set # channels before O/P rate"), "InvalidState");
+        assert_eq!(category(c"something new"), "Soxr");
     }
 }
@@ -4,7 +4,7 @@ use core::marker::PhantomData;
 use bytemuck::Pod;
 use libsoxr_sys as sys;
 
-use crate::buffer::{PlanarBuf, PlanarMut};
+use crate::buffer::{DynPlanarBuf, DynPlanarMut, PlanarBuf, PlanarMut};
 
 pub enum SampleFormat {
     Int16,
@@ -15,111 +15,267 @@ pub enum SampleFormat {
 
 pub unsafe trait Sample: Pod {
     const FORMAT: SampleFormat;
+
+    /// Sample value in its native scale
+    fn to_f64(self) -> f64;
+
+    /// Sample from value in its native scale, rounded and saturated for
+    /// integer samples
+    fn from_f64(value: f64) -> Self;
 }
 
 unsafe impl Sample for i16 {
     const FORMAT: SampleFormat = SampleFormat::Int16;
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        libm::round(value) as i16
+    }
 }
 
 unsafe impl Sample for i32 {
     const FORMAT: SampleFormat = SampleFormat::Int32;
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        libm::round(value) as i32
+    }
 }
 
 unsafe impl Sample for f32 {
     const FORMAT: SampleFormat = SampleFormat::Float32;
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
 }
 
 unsafe impl Sample for f64 {
     const FORMAT: SampleFormat = SampleFormat::Float64;
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+/// Type-level channel count of an [`IoFormat`], fixed at compile time
+pub struct Fixed<const CHANNELS: usize>;
+
+/// Implemented by channel counts fixed at compile time
+pub trait FixedChannels {
+    const CHANNELS: usize;
+}
+
+impl<const CHANNELS: usize> FixedChannels for Fixed<CHANNELS> {
+    const CHANNELS: usize = CHANNELS;
 }
 
+/// Type-level channel count of an [`IoFormat`], specified at runtime when
+/// the resampler is created
+pub struct Dynamic;
+
 pub unsafe trait IoFormat {
     type Sample: Sample;
+    /// Channel count at the type level. The input and output formats of a
+    /// resampler must agree on `Channels`
+    type Channels;
     type Input<'a>: ?Sized;
     type Output<'a>: ?Sized;
 
-    fn channels() -> usize;
     fn datatype() -> sys::soxr_datatype_t;
 
-    fn input_len<'a>(input: &Self::Input<'a>) -> usize;
+    /// Length of `input` in frames, given the resampler's channel count.
+    /// Panics if `input` does not hold `channels` channels.
+    fn input_len<'a>(input: &Self::Input<'a>, channels: usize) -> usize;
     fn input_ptr<'a>(input: &Self::Input<'a>) -> *const c_void;
 
-    fn output_len<'a>(output: &Self::Output<'a>) -> usize;
+    /// Length of `output` in frames, given the resampler's channel count.
+    /// Panics if `output` does not hold `channels` channels.
+    fn output_len<'a>(output: &Self::Output<'a>, channels: usize) -> usize;
     fn output_ptr<'a>(output: &mut Self::Output<'a>) -> *mut c_void;
 }
 
+/// Formats whose input and output buffers are plain slices of frames
+pub trait FrameFormat: IoFormat {
+    /// A single frame of audio, one sample per channel
+    type Frame: Pod;
+
+    fn input(frames: &[Self::Frame]) -> &Self::Input<'_>;
+    fn output(frames: &mut [Self::Frame]) -> &mut Self::Output<'_>;
+}
+
 /// Mono audio samples
 pub struct Mono<S: Sample>(PhantomData<S>);
 
 unsafe impl<S: Sample> IoFormat for Mono<S> {
     type Sample = S;
+    type Channels = Fixed<1>;
     type Input<'a> = [S];
     type Output<'a> = [S];
 
-    fn channels() -> usize { 1 }
     fn datatype() -> sys::soxr_datatype_t { interleaved::<S>() }
 
-    fn input_len<'a>(input: &Self::Input<'a>) -> usize { input.len() }
+    fn input_len<'a>(input: &Self::Input<'a>, _: usize) -> usize { input.len() }
     fn input_ptr<'a>(input: &Self::Input<'a>) -> *const c_void { input.as_ptr().cast() }
 
-    fn output_len<'a>(output: &Self::Output<'a>) -> usize { output.len() }
+    fn output_len<'a>(output: &Self::Output<'a>, _: usize) -> usize { output.len() }
     fn output_ptr<'a>(output: &mut Self::Output<'a>) -> *mut c_void { output.as_mut_ptr().cast() }
 }
 
+impl<S: Sample> FrameFormat for Mono<S> {
+    type Frame = S;
+
+    fn input(frames: &[Self::Frame]) -> &Self::Input<'_> { frames }
+    fn output(frames: &mut [Self::Frame]) -> &mut Self::Output<'_> { frames }
+}
+
 /// Stereo interleaved audio samples
 pub struct Stereo<S: Sample>(PhantomData<S>);
 
 unsafe impl<S: Sample> IoFormat for Stereo<S> {
     type Sample = S;
+    type Channels = Fixed<2>;
     type Input<'a> = [[S; 2]];
     type Output<'a> = [[S; 2]];
 
-    fn channels() -> usize { 2 }
     fn datatype() -> sys::soxr_datatype_t { interleaved::<S>() }
 
-    fn input_len<'a>(input: &Self::Input<'a>) -> usize { input.len() }
+    fn input_len<'a>(input: &Self::Input<'a>, _: usize) -> usize { input.len() }
     fn input_ptr<'a>(input: &Self::Input<'a>) -> *const c_void { input.as_ptr().cast() }
 
-    fn output_len<'a>(output: &Self::Output<'a>) -> usize { output.len() }
+    fn output_len<'a>(output: &Self::Output<'a>, _: usize) -> usize { output.len() }
     fn output_ptr<'a>(output: &mut Self::Output<'a>) -> *mut c_void { output.as_mut_ptr().cast() }
 }
 
+impl<S: Sample> FrameFormat for Stereo<S> {
+    type Frame = [S; 2];
+
+    fn input(frames: &[Self::Frame]) -> &Self::Input<'_> { frames }
+    fn output(frames: &mut [Self::Frame]) -> &mut Self::Output<'_> { frames }
+}
+
 /// N-channel interleaved audio samples
 pub struct Interleaved<S: Sample, const CHANNELS: usize>(PhantomData<S>);
 
 unsafe impl<S: Sample, const CHANNELS: usize> IoFormat for Interleaved<S, CHANNELS> {
     type Sample = S;
+    type Channels = Fixed<CHANNELS>;
     type Input<'a> = [[S; CHANNELS]];
     type Output<'a> = [[S; CHANNELS]];
 
-    fn channels() -> usize { CHANNELS }
     fn datatype() -> sys::soxr_datatype_t { interleaved::<S>() }
 
-    fn input_len<'a>(input: &Self::Input<'a>) -> usize { input.len() }
+    fn input_len<'a>(input: &Self::Input<'a>, _: usize) -> usize { input.len() }
     fn input_ptr<'a>(input: &Self::Input<'a>) -> *const c_void { input.as_ptr().cast() }
 
-    fn output_len<'a>(output: &Self::Output<'a>) -> usize { output.len() }
+    fn output_len<'a>(output: &Self::Output<'a>, _: usize) -> usize { output.len() }
     fn output_ptr<'a>(output: &mut Self::Output<'a>) -> *mut c_void { output.as_mut_ptr().cast() }
 }
 
+impl<S: Sample, const CHANNELS: usize> FrameFormat for Interleaved<S, CHANNELS> {
+    type Frame = [S; CHANNELS];
+
+    fn input(frames: &[Self::Frame]) -> &Self::Input<'_> { frames }
+    fn output(frames: &mut [Self::Frame]) -> &mut Self::Output<'_> { frames }
+}
+
 /// N-channel audio samples in planar buffers
 pub struct Planar<S: Sample, const CHANNELS: usize>(PhantomData<S>);
 
 unsafe impl<S: Sample, const CHANNELS: usize> IoFormat for Planar<S, CHANNELS> {
     type Sample = S;
+    type Channels = Fixed<CHANNELS>;
     type Input<'a> = PlanarBuf<'a, S, CHANNELS>;
     type Output<'a> = PlanarMut<'a, S, CHANNELS>;
 
-    fn channels() -> usize { CHANNELS }
     fn datatype() -> sys::soxr_datatype_t { planar::<S>() }
 
-    fn input_len<'a>(input: &Self::Input<'a>) -> usize { input.frames() }
+    fn input_len<'a>(input: &Self::Input<'a>, _: usize) -> usize { input.frames() }
     fn input_ptr<'a>(input: &Self::Input<'a>) -> *const c_void { input.as_ptr() }
 
-    fn output_len<'a>(output: &Self::Output<'a>) -> usize { output.frames() }
+    fn output_len<'a>(output: &Self::Output<'a>, _: usize) -> usize { output.frames() }
     fn output_ptr<'a>(output: &mut Self::Output<'a>) -> *mut c_void { output.as_ptr() }
 }
 
+/// Interleaved audio samples with channel count specified at runtime
+pub struct DynInterleaved<S: Sample>(PhantomData<S>);
+
+unsafe impl<S: Sample> IoFormat for DynInterleaved<S> {
+    type Sample = S;
+    type Channels = Dynamic;
+    type Input<'a> = [S];
+    type Output<'a> = [S];
+
+    fn datatype() -> sys::soxr_datatype_t { interleaved::<S>() }
+
+    fn input_len<'a>(input: &Self::Input<'a>, channels: usize) -> usize {
+        interleaved_frames(input.len(), channels)
+    }
+
+    fn input_ptr<'a>(input: &Self::Input<'a>) -> *const c_void { input.as_ptr().cast() }
+
+    fn output_len<'a>(output: &Self::Output<'a>, channels: usize) -> usize {
+        interleaved_frames(output.len(), channels)
+    }
+
+    fn output_ptr<'a>(output: &mut Self::Output<'a>) -> *mut c_void { output.as_mut_ptr().cast() }
+}
+
+/// Audio samples in planar buffers with channel count specified at runtime
+pub struct DynPlanar<S: Sample>(PhantomData<S>);
+
+unsafe impl<S: Sample> IoFormat for DynPlanar<S> {
+    type Sample = S;
+    type Channels = Dynamic;
+    type Input<'a> = DynPlanarBuf<'a, S>;
+    type Output<'a> = DynPlanarMut<'a, S>;
+
+    fn datatype() -> sys::soxr_datatype_t { planar::<S>() }
+
+    fn input_len<'a>(input: &Self::Input<'a>, channels: usize) -> usize {
+        check_planes(input.channels(), channels);
+        input.frames()
+    }
+
+    fn input_ptr<'a>(input: &Self::Input<'a>) -> *const c_void { input.as_ptr() }
+
+    fn output_len<'a>(output: &Self::Output<'a>, channels: usize) -> usize {
+        check_planes(output.channels(), channels);
+        output.frames()
+    }
+
+    fn output_ptr<'a>(output: &mut Self::Output<'a>) -> *mut c_void { output.as_ptr() }
+}
+
+fn interleaved_frames(samples: usize, channels: usize) -> usize {
+    if !samples.is_multiple_of(channels) {
+        panic!("interleaved buffer of {samples} samples does not hold whole frames \
+            of {channels} channels");
+    }
+
+    samples / channels
+}
+
+fn check_planes(planes: usize, channels: usize) {
+    if planes != channels {
+        panic!("planar buffer has {planes} planes, but resampler has {channels} channels");
+    }
+}
+
 fn interleaved<S: Sample>() -> sys::soxr_datatype_t {
     match S::FORMAT {
         SampleFormat::Int16 => sys::SOXR_INT16_I,
@@ -137,3 +293,20 @@ fn planar<S: Sample>() -> sys::soxr_datatype_t {
         SampleFormat::Float64 => sys::SOXR_FLOAT64_S,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{DynInterleaved, IoFormat};
+
+    #[test]
+    fn dyn_interleaved_len() {
+        assert_eq!(DynInterleaved::<f32>::input_len(&[0.0; 6], 2), 3);
+        assert_eq!(DynInterleaved::<f32>::output_len(&[0.0; 6], 3), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn dyn_interleaved_partial_frame() {
+        DynInterleaved::<f32>::input_len(&[0.0; 5], 2);
+    }
+}
@@ -0,0 +1,203 @@
+use alloc::vec::Vec;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+use futures_sink::Sink;
+
+use crate::format::FrameFormat;
+use crate::stream::ResamplerStream;
+use crate::{Error, Soxr};
+
+/// Stream adapter which resamples chunks of frames yielded by an inner
+/// stream. The resampler is drained when the inner stream ends.
+pub struct ResampleStream<St, In, Out = In>
+    where In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{
+    inner: St,
+    stream: ResamplerStream<In, Out>,
+    done: bool,
+}
+
+impl<St, In, Out> ResampleStream<St, In, Out>
+    where
+        St: Stream<Item = Vec<In::Frame>> + Unpin,
+        In: FrameFormat,
+        Out: FrameFormat<Channels = In::Channels>,
+{
+    pub fn new(inner: St, soxr: Soxr<In, Out>) -> Self {
+        ResampleStream {
+            inner,
+            stream: ResamplerStream::new(soxr),
+            done: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &St {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut St {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> St {
+        self.inner
+    }
+}
+
+// the inner stream is only ever pinned through `Pin::new`, which requires
+// it to be `Unpin`
+impl<St: Unpin, In, Out> Unpin for ResampleStream<St, In, Out>
+    where In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{}
+
+impl<St, In, Out> Stream for ResampleStream<St, In, Out>
+    where
+        St: Stream<Item = Vec<In::Frame>> + Unpin,
+        In: FrameFormat,
+        Out: FrameFormat<Channels = In::Channels>,
+{
+    type Item = Result<Vec<Out::Frame>, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            let mut output = Vec::new();
+
+            let result = match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(input) => this.stream.push_into(&input, &mut output),
+                None => {
+                    this.done = true;
+                    this.stream.finish_into(&mut output)
+                }
+            };
+
+            if let Err(error) = result {
+                this.done = true;
+                return Poll::Ready(Some(Err(error)));
+            }
+
+            if !output.is_empty() {
+                return Poll::Ready(Some(Ok(output)));
+            }
+        }
+    }
+}
+
+/// Sink adapter which resamples chunks of frames before sending them to an
+/// inner sink. The resampler is drained when the sink is closed.
+pub struct ResampleSink<Si, In, Out = In>
+    where In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{
+    inner: Si,
+    stream: ResamplerStream<In, Out>,
+    /// Output waiting to be sent to the inner sink
+    pending: Option<Vec<Out::Frame>>,
+    finished: bool,
+}
+
+impl<Si, In, Out> ResampleSink<Si, In, Out>
+    where
+        Si: Sink<Vec<Out::Frame>> + Unpin,
+        Si::Error: From<Error>,
+        In: FrameFormat,
+        Out: FrameFormat<Channels = In::Channels>,
+{
+    pub fn new(inner: Si, soxr: Soxr<In, Out>) -> Self {
+        ResampleSink {
+            inner,
+            stream: ResamplerStream::new(soxr),
+            pending: None,
+            finished: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &Si {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut Si {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> Si {
+        self.inner
+    }
+
+    /// Send any pending output to the inner sink
+    fn poll_send_pending(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Si::Error>> {
+        if self.pending.is_some() {
+            ready!(Pin::new(&mut self.inner).poll_ready(cx))?;
+
+            if let Some(output) = self.pending.take() {
+                Pin::new(&mut self.inner).start_send(output)?;
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<Si, In, Out> Unpin for ResampleSink<Si, In, Out>
+    where Si: Unpin, In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{}
+
+impl<Si, In, Out> Sink<Vec<In::Frame>> for ResampleSink<Si, In, Out>
+    where
+        Si: Sink<Vec<Out::Frame>> + Unpin,
+        Si::Error: From<Error>,
+        In: FrameFormat,
+        Out: FrameFormat<Channels = In::Channels>,
+{
+    type Error = Si::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_send_pending(cx))?;
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, input: Vec<In::Frame>) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+
+        let mut output = this.pending.take().unwrap_or_default();
+        let result = this.stream.push_into(&input, &mut output);
+
+        if !output.is_empty() {
+            this.pending = Some(output);
+        }
+
+        Ok(result?)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_send_pending(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        if !this.finished {
+            let mut output = this.pending.take().unwrap_or_default();
+            let result = this.stream.finish_into(&mut output);
+            this.finished = true;
+
+            if !output.is_empty() {
+                this.pending = Some(output);
+            }
+
+            result?;
+        }
+
+        ready!(this.poll_send_pending(cx))?;
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
@@ -0,0 +1,214 @@
+use std::io::{self, Read, Write};
+use std::vec::Vec;
+
+use bytemuck::Zeroable;
+
+use crate::format::{FrameFormat, IoFormat};
+use crate::stream::ResamplerStream;
+use crate::Soxr;
+
+/// Number of bytes read from the inner reader at a time
+const READ_CHUNK: usize = 8192;
+
+/// Reader which resamples raw little-endian PCM read from an inner reader
+pub struct ResampleReader<R, In, Out = In>
+    where In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{
+    inner: R,
+    stream: ResamplerStream<In, Out>,
+    /// Bytes read from inner reader but not yet resampled, less than one
+    /// frame once converted
+    input_bytes: Vec<u8>,
+    input: Vec<In::Frame>,
+    output: Vec<Out::Frame>,
+    /// Byte offset of next unread output
+    output_pos: usize,
+    finished: bool,
+}
+
+impl<R, In, Out> ResampleReader<R, In, Out>
+    where R: Read, In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{
+    pub fn new(inner: R, soxr: Soxr<In, Out>) -> Self {
+        ResampleReader {
+            inner,
+            stream: ResamplerStream::new(soxr),
+            input_bytes: Vec::new(),
+            input: Vec::new(),
+            output: Vec::new(),
+            output_pos: 0,
+            finished: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read from the inner reader and resample into `output`. Returns
+    /// `false` once input and resampler are both exhausted.
+    fn fill_output(&mut self) -> io::Result<bool> {
+        self.output.clear();
+        self.output_pos = 0;
+
+        if self.finished {
+            return Ok(false);
+        }
+
+        let start = self.input_bytes.len();
+        self.input_bytes.resize(start + READ_CHUNK, 0);
+        let result = self.inner.read(&mut self.input_bytes[start..]);
+        let read = *result.as_ref().unwrap_or(&0);
+        self.input_bytes.truncate(start + read);
+        result?;
+
+        if read == 0 {
+            // any trailing partial frame is dropped
+            self.finished = true;
+            self.stream.finish_into(&mut self.output).map_err(io::Error::other)?;
+        } else {
+            let frames = decode::<In>(&mut self.input_bytes, &mut self.input);
+            self.stream.push_into(frames, &mut self.output).map_err(io::Error::other)?;
+        }
+
+        encode::<Out>(&mut self.output);
+        Ok(true)
+    }
+}
+
+impl<R, In, Out> Read for ResampleReader<R, In, Out>
+    where R: Read, In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let output = bytemuck::cast_slice::<_, u8>(&self.output);
+            let remaining = &output[self.output_pos..];
+
+            if !remaining.is_empty() {
+                let len = remaining.len().min(buf.len());
+                buf[..len].copy_from_slice(&remaining[..len]);
+                self.output_pos += len;
+                return Ok(len);
+            }
+
+            if !self.fill_output()? {
+                return Ok(0);
+            }
+        }
+    }
+}
+
+/// Writer which resamples raw little-endian PCM and writes it to an inner
+/// writer. Call `finish` at the end of the stream to drain the resampler.
+pub struct ResampleWriter<W, In, Out = In>
+    where W: Write, In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{
+    inner: W,
+    stream: ResamplerStream<In, Out>,
+    /// Bytes written but not yet resampled, less than one frame once
+    /// converted
+    input_bytes: Vec<u8>,
+    input: Vec<In::Frame>,
+    output: Vec<Out::Frame>,
+}
+
+impl<W, In, Out> ResampleWriter<W, In, Out>
+    where W: Write, In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{
+    pub fn new(inner: W, soxr: Soxr<In, Out>) -> Self {
+        ResampleWriter {
+            inner,
+            stream: ResamplerStream::new(soxr),
+            input_bytes: Vec::new(),
+            input: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Drain the resampler, writing all remaining output to the inner
+    /// writer, and return it. Any trailing partial frame is dropped.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.output.clear();
+        self.stream.finish_into(&mut self.output).map_err(io::Error::other)?;
+        self.write_output()?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_output(&mut self) -> io::Result<()> {
+        encode::<Out>(&mut self.output);
+        self.inner.write_all(bytemuck::cast_slice(&self.output))
+    }
+}
+
+impl<W, In, Out> Write for ResampleWriter<W, In, Out>
+    where W: Write, In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.input_bytes.extend_from_slice(buf);
+
+        self.output.clear();
+        let frames = decode::<In>(&mut self.input_bytes, &mut self.input);
+        self.stream.push_into(frames, &mut self.output).map_err(io::Error::other)?;
+        self.write_output()?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Convert whole frames from the start of `bytes` into `frames`, leaving
+/// any trailing partial frame in `bytes`
+fn decode<'a, F: FrameFormat>(bytes: &mut Vec<u8>, frames: &'a mut Vec<F::Frame>)
+    -> &'a [F::Frame]
+{
+    let frame_size = core::mem::size_of::<F::Frame>();
+    let count = bytes.len() / frame_size;
+
+    frames.clear();
+    frames.resize(count, Zeroable::zeroed());
+
+    let frame_bytes = bytemuck::cast_slice_mut::<_, u8>(frames.as_mut_slice());
+    frame_bytes.copy_from_slice(&bytes[..frame_bytes.len()]);
+    bytes.drain(..frame_bytes.len());
+
+    swap_le::<F>(frames);
+    frames
+}
+
+/// Convert frames from native to little-endian byte order, in place
+fn encode<F: FrameFormat>(frames: &mut [F::Frame]) {
+    swap_le::<F>(frames);
+}
+
+/// Swap samples between native and little-endian byte order. No-op on
+/// little-endian targets.
+fn swap_le<F: FrameFormat>(frames: &mut [F::Frame]) {
+    if cfg!(target_endian = "big") {
+        let sample_size = core::mem::size_of::<<F as IoFormat>::Sample>();
+        let bytes = bytemuck::cast_slice_mut::<_, u8>(frames);
+
+        for sample in bytes.chunks_exact_mut(sample_size) {
+            sample.reverse();
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::format::{FixedChannels, FrameFormat, Interleaved, Mono, Sample};
+use crate::params::{QualitySpec, RuntimeSpec};
+use crate::{Error, Soxr};
+
+/// Number of frames batched into each call to `Soxr::process`
+const CHUNK: usize = 256;
+
+/// Frame types which can be resampled with [`ResampleExt::resample`]. Bare
+/// samples are treated as mono, and arrays of samples as interleaved frames.
+pub trait Frame: Pod {
+    type Format: FrameFormat<Frame = Self, Channels: FixedChannels>;
+}
+
+impl<S: Sample> Frame for S {
+    type Format = Mono<S>;
+}
+
+impl<S: Sample, const CHANNELS: usize> Frame for [S; CHANNELS] {
+    type Format = Interleaved<S, CHANNELS>;
+}
+
+/// Extension trait adding lazy resampling to iterators of frames
+pub trait ResampleExt: Iterator + Sized
+    where Self::Item: Frame
+{
+    /// Resample frames yielded by this iterator from `input_rate` to
+    /// `output_rate`. Frames are pulled from the iterator in batches as
+    /// output is consumed.
+    fn resample(self, input_rate: f64, output_rate: f64, quality: QualitySpec)
+        -> Result<Resample<Self>, Error>
+    {
+        let soxr = Soxr::new_with_params(
+            input_rate,
+            output_rate,
+            quality,
+            RuntimeSpec::default(),
+        )?;
+
+        Ok(Resample {
+            iter: self,
+            soxr,
+            state: State::Running,
+            exhausted: false,
+            error: None,
+            input: Zeroable::zeroed(),
+            input_start: 0,
+            input_end: 0,
+            output: Zeroable::zeroed(),
+            output_start: 0,
+            output_end: 0,
+        })
+    }
+}
+
+impl<I: Iterator> ResampleExt for I where I::Item: Frame {}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Processing input from inner iterator
+    Running,
+    /// All input processed, draining resampler
+    Draining,
+    /// Resampler drained
+    Done,
+}
+
+/// Iterator returned by [`ResampleExt::resample`]. If the resampler
+/// returns an error, iteration ends early and the error is available from
+/// [`Resample::error`].
+pub struct Resample<I: Iterator> where I::Item: Frame {
+    iter: I,
+    soxr: Soxr<<I::Item as Frame>::Format>,
+    state: State,
+    /// Whether the inner iterator has returned `None`
+    exhausted: bool,
+    error: Option<Error>,
+    input: [I::Item; CHUNK],
+    input_start: usize,
+    input_end: usize,
+    output: [I::Item; CHUNK],
+    output_start: usize,
+    output_end: usize,
+}
+
+impl<I: Iterator> Resample<I> where I::Item: Frame {
+    /// Error which ended iteration early, if any
+    pub fn error(&self) -> Option<Error> {
+        self.error
+    }
+
+    pub fn soxr(&self) -> &Soxr<<I::Item as Frame>::Format> {
+        &self.soxr
+    }
+
+    /// Top up the input buffer from the inner iterator
+    fn fill_input(&mut self) {
+        self.input.copy_within(self.input_start..self.input_end, 0);
+        self.input_end -= self.input_start;
+        self.input_start = 0;
+
+        while self.input_end < CHUNK {
+            let Some(frame) = self.iter.next() else {
+                self.exhausted = true;
+                break;
+            };
+
+            self.input[self.input_end] = frame;
+            self.input_end += 1;
+        }
+    }
+
+    fn fail(&mut self, error: Error) {
+        self.error = Some(error);
+        self.state = State::Done;
+    }
+}
+
+impl<I: Iterator> Iterator for Resample<I> where I::Item: Frame {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        type Format<I> = <<I as Iterator>::Item as Frame>::Format;
+
+        loop {
+            if self.output_start < self.output_end {
+                let frame = self.output[self.output_start];
+                self.output_start += 1;
+                return Some(frame);
+            }
+
+            self.output_start = 0;
+            self.output_end = 0;
+
+            match self.state {
+                State::Done => return None,
+                State::Draining => {
+                    match self.soxr.drain(Format::<I>::output(&mut self.output)) {
+                        Ok(0) => self.state = State::Done,
+                        Ok(produced) => self.output_end = produced,
+                        Err(error) => self.fail(error),
+                    }
+                }
+                State::Running => {
+                    if !self.exhausted && self.input_end - self.input_start < CHUNK {
+                        self.fill_input();
+                    }
+
+                    if self.input_start == self.input_end {
+                        self.state = State::Draining;
+                        continue;
+                    }
+
+                    let result = self.soxr.process(
+                        Format::<I>::input(&self.input[self.input_start..self.input_end]),
+                        Format::<I>::output(&mut self.output),
+                    );
+
+                    match result {
+                        Ok(processed) => {
+                            self.input_start += processed.input_frames;
+                            self.output_end = processed.output_frames;
+                        }
+                        Err(error) => self.fail(error),
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use bytemuck::Zeroable;
+
+#[cfg(feature = "alloc")]
+use crate::format::{FixedChannels, FrameFormat};
+#[cfg(feature = "alloc")]
+use crate::params::QualitySpec;
+#[cfg(feature = "alloc")]
+use crate::stream::ResamplerStream;
+#[cfg(feature = "alloc")]
+use crate::{Error, Soxr};
+
+/// Exact number of output frames produced by resampling `input_frames`
+/// frames from `input_rate` to `output_rate` at a fixed ratio, once the
+/// resampler is drained: `round(input_frames * output_rate / input_rate)`
+pub fn output_frames(input_frames: usize, input_rate: f64, output_rate: f64) -> usize {
+    libm::round(input_frames as f64 * output_rate / input_rate) as usize
+}
+
+/// Resample a whole clip offline, producing exactly
+/// [`output_frames`]`(input.len(), input_rate, output_rate)` frames.
+///
+/// libsoxr compensates for filter delay, so output is already aligned with
+/// the input. Output beyond the exact length is removed from the end; if
+/// the resampler produces fewer frames, the end is padded with silence.
+#[cfg(feature = "alloc")]
+pub fn resample_exact<In, Out>(
+    input_rate: f64,
+    output_rate: f64,
+    input: &[In::Frame],
+    quality: QualitySpec,
+) -> Result<Vec<Out::Frame>, Error>
+    where
+        In: FrameFormat,
+        In::Channels: FixedChannels,
+        Out: FrameFormat<Channels = In::Channels>,
+{
+    let soxr = Soxr::<In, Out>::new_with_params(
+        input_rate,
+        output_rate,
+        quality,
+        Default::default(),
+    )?;
+
+    let mut stream = ResamplerStream::new(soxr);
+    let mut output = Vec::new();
+    stream.push_into(input, &mut output)?;
+    stream.finish_into(&mut output)?;
+
+    let exact_len = output_frames(input.len(), input_rate, output_rate);
+
+    output.resize(exact_len, Zeroable::zeroed());
+
+    Ok(output)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use crate::format::Mono;
+    use crate::params::QualitySpec;
+
+    use super::{output_frames, resample_exact};
+
+    #[test]
+    fn exact_length_and_alignment() {
+        let mut input = [0.0f32; 1000];
+        input[500] = 1.0;
+
+        let output = resample_exact::<Mono<f32>, Mono<f32>>(
+            44100.0,
+            48000.0,
+            &input,
+            QualitySpec::default(),
+        ).unwrap();
+
+        assert_eq!(output.len(), output_frames(1000, 44100.0, 48000.0));
+
+        let peak = output.iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+            .map(|(index, _)| index)
+            .unwrap();
+
+        assert_eq!(peak, output_frames(500, 44100.0, 48000.0));
+    }
+}
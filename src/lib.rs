@@ -1,40 +1,100 @@
 #![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod adaptive;
+
+#[cfg(feature = "alloc")]
+pub mod analysis;
+
 pub mod buffer;
+pub mod builder;
 pub mod error;
 pub mod format;
+
+#[cfg(feature = "futures")]
+pub mod futures;
+
+#[cfg(feature = "std")]
+pub mod io;
+
+pub mod iter;
+pub mod length;
+pub mod oneshot;
 pub mod params;
 pub mod raw;
+pub mod source;
+
+#[cfg(feature = "alloc")]
+pub mod stream;
+
+pub mod timestamp;
+pub mod varispeed;
 
+pub use builder::SoxrBuilder;
 pub use error::Error;
+pub use oneshot::oneshot;
+
+#[cfg(feature = "alloc")]
+pub use oneshot::oneshot_vec;
+
+#[cfg(feature = "alloc")]
+pub use stream::ResamplerStream;
 
-use core::ffi::c_uint;
+use core::any::type_name;
+use core::ffi::{c_char, c_uint, c_void, CStr};
+use core::fmt::{self, Debug};
 use core::{marker::PhantomData, ptr::null};
 use core::ptr::null_mut;
 
 use libsoxr_sys as sys;
 
-use format::IoFormat;
-use params::{QualitySpec, RuntimeSpec, QualityRecipe};
+use format::{Dynamic, FixedChannels, IoFormat};
+use params::{IoSpec, QualitySpec, RuntimeSpec};
 use raw::SoxrPtr;
+use source::Source;
 
 pub type ChannelCount = usize;
 
-pub struct Soxr<Format: IoFormat> {
+/// Resampler converting from `In` format to `Out` format. Input and output
+/// formats may differ in sample type and layout (interleaved or planar), but
+/// must agree on channel count.
+pub struct Soxr<In: IoFormat, Out: IoFormat = In> {
     soxr: SoxrPtr,
-    _phantom: PhantomData<Format>,
+    channels: ChannelCount,
+    input_rate: f64,
+    output_rate: f64,
+    /// Input/output ratio, as given by the rates or by `set_io_ratio`
+    io_ratio: f64,
+    /// Parameters given at creation, kept to recreate the resampler
+    params: Params,
+    /// Input frames consumed since creation or last `clear`
+    total_input: usize,
+    /// Output frames produced since creation or last `clear`
+    total_output: usize,
+    /// Whether `drain` has been called since creation or last `clear`
+    drained: bool,
+    _phantom: PhantomData<(In, Out)>,
 }
 
-impl<Format: IoFormat> Soxr<Format> {
+impl<In, Out> Soxr<In, Out>
+    where In: IoFormat, In::Channels: FixedChannels, Out: IoFormat<Channels = In::Channels>
+{
+    /// Creates a builder for a resampler, see [`SoxrBuilder`]
+    pub fn builder() -> SoxrBuilder<In, Out> {
+        SoxrBuilder::new(In::Channels::CHANNELS)
+    }
+
     /// Creates a new resampler instance using default values for quality
     /// and runtime parameters
     pub fn new(input_rate: f64, output_rate: f64) -> Result<Self, Error> {
-        Self::new_with_params(
-            input_rate,
-            output_rate,
-            QualitySpec::default(),
-            RuntimeSpec::default(),
-        )
+        Self::builder()
+            .rates(input_rate, output_rate)
+            .build()
     }
 
     /// Creates a new variable rate resampler instance using default values
@@ -42,12 +102,10 @@ impl<Format: IoFormat> Soxr<Format> {
     pub fn variable_rate(input_rate: f64, output_rate: f64)
         -> Result<Self, Error>
     {
-        Self::new_with_params(
-            input_rate,
-            output_rate,
-            QualitySpec::variable_rate(QualityRecipe::default()),
-            RuntimeSpec::default(),
-        )
+        Self::builder()
+            .rates(input_rate, output_rate)
+            .variable_rate()
+            .build()
     }
 
     /// Creates a new resampler instance with the specified quality and
@@ -58,103 +116,332 @@ impl<Format: IoFormat> Soxr<Format> {
         quality: QualitySpec,
         runtime: RuntimeSpec,
     ) -> Result<Self, Error> {
-        let io = sys::soxr_io_spec {
-            itype: Format::datatype(),
-            otype: Format::datatype(),
-            scale: 1.0,
-            e: null_mut(),
-            flags: 0,
-        };
+        Self::builder()
+            .rates(input_rate, output_rate)
+            .quality_spec(quality)
+            .runtime_spec(runtime)
+            .build()
+    }
+
+    /// Creates a new resampler instance with the specified quality, runtime
+    /// and IO parameters
+    pub fn new_with_io_spec(
+        input_rate: f64,
+        output_rate: f64,
+        quality: QualitySpec,
+        runtime: RuntimeSpec,
+        io: IoSpec,
+    ) -> Result<Self, Error> {
+        Self::builder()
+            .rates(input_rate, output_rate)
+            .quality_spec(quality)
+            .runtime_spec(runtime)
+            .io_spec(io)
+            .build()
+    }
+}
+
+impl<In, Out> Soxr<In, Out>
+    where In: IoFormat<Channels = Dynamic>, Out: IoFormat<Channels = Dynamic>
+{
+    /// Creates a builder for a resampler with a runtime channel count, see
+    /// [`SoxrBuilder`]
+    pub fn dynamic_builder(channels: ChannelCount) -> SoxrBuilder<In, Out> {
+        SoxrBuilder::new(channels)
+    }
+
+    /// Creates a new resampler instance for formats with a runtime channel
+    /// count, using default values for quality and runtime parameters
+    pub fn new_dynamic(input_rate: f64, output_rate: f64, channels: ChannelCount)
+        -> Result<Self, Error>
+    {
+        Self::dynamic_builder(channels)
+            .rates(input_rate, output_rate)
+            .build()
+    }
 
-        let channels = c_uint::try_from(Format::channels())
-            .map_err(|_| error::CHANNEL_COUNT_TOO_LARGE)?;
+    /// Creates a new resampler instance for formats with a runtime channel
+    /// count, with the specified quality, runtime and IO parameters
+    pub fn new_dynamic_with_params(
+        input_rate: f64,
+        output_rate: f64,
+        channels: ChannelCount,
+        quality: QualitySpec,
+        runtime: RuntimeSpec,
+        io: IoSpec,
+    ) -> Result<Self, Error> {
+        Self::dynamic_builder(channels)
+            .rates(input_rate, output_rate)
+            .quality_spec(quality)
+            .runtime_spec(runtime)
+            .io_spec(io)
+            .build()
+    }
 
-        let soxr = unsafe {
-            let mut error = null();
+    /// Change the number of channels processed by the resampler. Buffers
+    /// passed to subsequent calls must hold the new number of channels.
+    ///
+    /// libsoxr cannot change the channel count of an existing resampler, so
+    /// it is recreated at the current rates and io ratio with the parameters
+    /// it was created with. As with `clear`, any buffered audio is discarded and
+    /// the resampler is reset to its initial state. Does nothing if the
+    /// channel count is unchanged.
+    pub fn set_num_channels(&mut self, channels: ChannelCount) -> Result<(), Error> {
+        if channels == 0 {
+            return Err(error::NO_CHANNELS);
+        }
+
+        if channels == self.channels {
+            return Ok(());
+        }
 
-            let ptr = sys::soxr_create(
-                input_rate,
-                output_rate,
-                channels,
-                &mut error,
-                &io,
-                quality.as_raw(),
-                runtime.as_raw(),
-            );
+        let soxr = create_raw(self.input_rate, self.output_rate, channels, &self.params)?;
 
-            if ptr == null_mut() {
-                return Err(Error::from_raw(error));
+        // only variable rate resamplers accept a ratio other than that given
+        // by the rates
+        if self.io_ratio != self.input_rate / self.output_rate {
+            unsafe {
+                Error::check(sys::soxr_set_io_ratio(soxr.as_ptr(), self.io_ratio, 0))?;
             }
+        }
+
+        self.soxr = soxr;
+        self.channels = channels;
+        self.drained = false;
+        self.total_input = 0;
+        self.total_output = 0;
+        Ok(())
+    }
+}
 
-            SoxrPtr::from_raw(ptr)
+impl<In, Out> Soxr<In, Out>
+    where In: IoFormat, Out: IoFormat<Channels = In::Channels>
+{
+    pub(crate) fn create(
+        input_rate: f64,
+        output_rate: f64,
+        channels: ChannelCount,
+        quality: QualitySpec,
+        runtime: RuntimeSpec,
+        io: IoSpec,
+    ) -> Result<Self, Error> {
+        quality.validate()?;
+
+        let params = Params {
+            quality,
+            runtime,
+            io: sys::soxr_io_spec {
+                itype: In::datatype(),
+                otype: Out::datatype(),
+                ..*io.as_raw()
+            },
         };
 
+        let soxr = create_raw(input_rate, output_rate, channels, &params)?;
+
         Ok(Soxr {
             soxr,
+            channels,
+            input_rate,
+            output_rate,
+            io_ratio: input_rate / output_rate,
+            params,
+            total_input: 0,
+            total_output: 0,
+            drained: false,
             _phantom: PhantomData,
         })
     }
 
+    /// Number of channels the resampler was created with
+    pub fn channels(&self) -> ChannelCount {
+        self.channels
+    }
+
     pub fn as_ptr(&self) -> sys::soxr_t {
         self.soxr.as_ptr()
     }
 
+    /// Name of the DSP engine selected by libsoxr, eg. `cr32s` for the SIMD
+    /// engine or `cr32`/`cr64` for the scalar engines
+    pub fn engine(&self) -> &'static str {
+        unsafe { static_str(sys::soxr_engine(self.as_ptr())) }
+    }
+
     /// Process audio through the sampler. Once finished, call `drain` until
-    /// it returns `0``.
-    pub fn process<'a>(&mut self, input: &Format::Input<'a>, output: &mut Format::Output<'a>)
+    /// it returns `0``. Returns [`Error::InvalidState`] if called after
+    /// `drain` without an intervening `clear`.
+    pub fn process<'a>(&mut self, input: &In::Input<'a>, output: &mut Out::Output<'a>)
         -> Result<Processed, Error>
     {
-        let input_len = Format::input_len(input);
-        let output_len = Format::output_len(output);
+        if self.drained {
+            return Err(error::PROCESS_AFTER_DRAIN);
+        }
+
+        let input_len = In::input_len(input, self.channels);
+        let output_len = Out::output_len(output, self.channels);
+
+        let processed = unsafe {
+            let input_ptr = In::input_ptr(input);
+            let output_ptr = Out::output_ptr(output);
+
+            self.process_raw(input_ptr, input_len, output_ptr, output_len)?
+        };
+
+        self.total_input += processed.input_frames;
+        self.total_output += processed.output_frames;
+
+        Ok(processed)
+    }
+
+    /// Indicate to the resampler that the input stream has finished, and
+    /// read remaining buffered data out of resampler
+    pub fn drain<'a>(&mut self, output: &mut Out::Output<'a>) -> Result<usize, Error> {
+        let output_len = Out::output_len(output, self.channels);
+
+        self.drained = true;
+
+        let processed = unsafe {
+            let output_ptr = Out::output_ptr(output);
+
+            self.process_raw(null(), 0, output_ptr, output_len)?
+        };
+
+        self.total_output += processed.output_frames;
+
+        Ok(processed.output_frames)
+    }
 
+    /// Calls `soxr_process`. Null `input_ptr` indicates end of input.
+    unsafe fn process_raw(
+        &mut self,
+        input_ptr: *const c_void,
+        input_len: usize,
+        output_ptr: *mut c_void,
+        output_len: usize,
+    ) -> Result<Processed, Error> {
         let mut input_consumed = 0;
         let mut output_produced = 0;
 
-        unsafe {
-            let input_ptr = Format::input_ptr(input);
-            let output_ptr = Format::output_ptr(output);
+        let clips_before = self.num_clips();
 
-            Error::check(sys::soxr_process(
-                self.as_ptr(),
-                input_ptr,
-                input_len,
-                &mut input_consumed,
-                output_ptr,
-                output_len,
-                &mut output_produced,
-            ))?;
-        }
+        Error::check(sys::soxr_process(
+            self.as_ptr(),
+            input_ptr,
+            input_len,
+            &mut input_consumed,
+            output_ptr,
+            output_len,
+            &mut output_produced,
+        ))?;
 
         Ok(Processed {
             input_frames: input_consumed,
             output_frames: output_produced,
+            clips: self.num_clips() - clips_before,
         })
     }
 
-    /// Indicate to the resampler that the input stream has finished, and
-    /// read remaining buffered data out of resampler
-    pub fn drain<'a>(&mut self, output: &mut Format::Output<'a>) -> Result<usize, Error> {
-        let output_len = Format::output_len(output);
-        let mut output_produced = 0;
+    /// Pull resampled audio into `output`, requesting input from `source` as
+    /// needed. Returns the number of frames written, which is less than the
+    /// length of `output` only once `source` has signalled the end of input
+    /// and the resampler has been drained.
+    ///
+    /// `source` is only registered with the resampler for the duration of
+    /// this call. Panicking inside [`Source::supply`] aborts the process.
+    pub fn output<'a, S>(&mut self, source: &mut S, output: &mut Out::Output<'a>)
+        -> Result<usize, Error>
+        where S: Source<In> + ?Sized
+    {
+        let output_len = Out::output_len(output, self.channels);
+        let mut state = source::InputFnState {
+            source,
+            channels: self.channels,
+            frames_supplied: 0,
+        };
 
         unsafe {
-            let output_ptr = Format::output_ptr(output);
+            let output_ptr = Out::output_ptr(output);
 
-            Error::check(sys::soxr_process(
+            Error::check(sys::soxr_set_input_fn(
                 self.as_ptr(),
-                null(),
-                0,
-                null_mut(),
-                output_ptr,
-                output_len,
-                &mut output_produced,
+                Some(source::input_fn::<In, S>),
+                (&mut state as *mut source::InputFnState<S>).cast(),
+                usize::MAX,
             ))?;
+
+            let output_produced = sys::soxr_output(self.as_ptr(), output_ptr, output_len);
+
+            // unregister source so that the resampler never holds on to a
+            // dangling pointer to it
+            sys::soxr_set_input_fn(self.as_ptr(), None, null_mut(), 0);
+
+            self.total_input += state.frames_supplied;
+
+            Error::check(sys::soxr_error(self.as_ptr()))?;
+
+            self.total_output += output_produced;
+
+            Ok(output_produced)
         }
+    }
+
+    /// Input rate, as given at creation or by `set_rates`
+    pub fn input_rate(&self) -> f64 {
+        self.input_rate
+    }
+
+    /// Output rate, as given at creation or by `set_rates`
+    pub fn output_rate(&self) -> f64 {
+        self.output_rate
+    }
+
+    /// Input/output ratio the resampler is converging on, as given by the
+    /// rates or by `set_io_ratio`
+    pub fn io_ratio(&self) -> f64 {
+        self.io_ratio
+    }
 
-        Ok(output_produced)
+    /// Total input frames consumed since creation or last `clear`, by
+    /// either `process` or `output`
+    pub fn total_input_frames(&self) -> usize {
+        self.total_input
     }
 
+    /// Total output frames produced since creation or last `clear`
+    pub fn total_output_frames(&self) -> usize {
+        self.total_output
+    }
+
+    /// Total output frames the input consumed so far will have produced
+    /// once the resampler is drained. Only exact while the io ratio is
+    /// unchanged from that given by the rates.
+    pub fn expected_output_frames(&self) -> usize {
+        length::output_frames(self.total_input, self.input_rate, self.output_rate)
+    }
+
+    /// Output frames still to come from input consumed so far, including
+    /// those produced by draining. See `expected_output_frames`.
+    pub fn remaining_output_frames(&self) -> usize {
+        self.expected_output_frames().saturating_sub(self.total_output)
+    }
+
+    /// Amount of buffered data in the resampler, in output frames
+    pub fn delay(&self) -> f64 {
+        unsafe { sys::soxr_delay(self.as_ptr()) }
+    }
+
+    /// Running total of output samples clipped during conversion to integer
+    /// output formats
+    pub fn num_clips(&self) -> usize {
+        unsafe { *sys::soxr_num_clips(self.as_ptr()) }
+    }
+
+    /// Reset the resampler to its initial state
     pub fn clear(&mut self) -> Result<(), Error> {
+        self.drained = false;
+        self.total_input = 0;
+        self.total_output = 0;
         unsafe { Error::check(sys::soxr_clear(self.as_ptr())) }
     }
 
@@ -164,7 +451,10 @@ impl<Format: IoFormat> Soxr<Format> {
     pub fn set_rates(&mut self, input_rate: f64, output_rate: f64, slew_len: usize)
         -> Result<(), Error>
     {
-        self.set_io_ratio(input_rate / output_rate, slew_len)
+        self.set_io_ratio(input_rate / output_rate, slew_len)?;
+        self.input_rate = input_rate;
+        self.output_rate = output_rate;
+        Ok(())
     }
 
     /// Change the resampler's input/output sample ratio, smoothly changing
@@ -178,12 +468,218 @@ impl<Format: IoFormat> Soxr<Format> {
                 self.as_ptr(),
                 ratio,
                 slew_len,
-            ))
+            ))?;
         }
+
+        self.io_ratio = ratio;
+        Ok(())
+    }
+}
+
+impl<In, Out> Debug for Soxr<In, Out>
+    where In: IoFormat, Out: IoFormat<Channels = In::Channels>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Soxr")
+            .field("input_rate", &self.input_rate)
+            .field("output_rate", &self.output_rate)
+            .field("input_format", &type_name::<In>())
+            .field("output_format", &type_name::<Out>())
+            .field("channels", &self.channels)
+            .field("engine", &self.engine())
+            .finish()
+    }
+}
+
+/// Quality, runtime and IO parameters a resampler was created with
+struct Params {
+    quality: QualitySpec,
+    runtime: RuntimeSpec,
+    io: sys::soxr_io_spec,
+}
+
+// the `e` pointers in each spec are either null or point to static libsoxr
+// error messages
+unsafe impl Send for Params {}
+unsafe impl Sync for Params {}
+
+fn create_raw(input_rate: f64, output_rate: f64, channels: ChannelCount, params: &Params)
+    -> Result<SoxrPtr, Error>
+{
+    let raw_channels = c_uint::try_from(channels)
+        .map_err(|_| error::CHANNEL_COUNT_TOO_LARGE)?;
+
+    unsafe {
+        let mut error = null();
+
+        let ptr = sys::soxr_create(
+            input_rate,
+            output_rate,
+            raw_channels,
+            &mut error,
+            &params.io,
+            params.quality.as_raw(),
+            params.runtime.as_raw(),
+        );
+
+        if ptr == null_mut() {
+            return Err(Error::from_raw(error));
+        }
+
+        Ok(SoxrPtr::from_raw(ptr))
     }
 }
 
+/// Version of libsoxr in use, eg. `libsoxr-0.1.3`
+pub fn version() -> &'static str {
+    unsafe { static_str(sys::soxr_version()) }
+}
+
+/// Convert a static string returned by libsoxr
+unsafe fn static_str(ptr: *const c_char) -> &'static str {
+    if ptr.is_null() {
+        return "";
+    }
+
+    CStr::from_ptr(ptr).to_str().unwrap_or("")
+}
+
 pub struct Processed {
     pub input_frames: usize,
     pub output_frames: usize,
+    /// Number of output samples clipped during integer conversion
+    pub clips: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{DynInterleaved, Mono};
+    use crate::source::Source;
+    use crate::{Error, Soxr};
+
+    /// Source supplying a fixed number of frames of silence
+    struct Silence {
+        remaining: usize,
+        buffer: [f32; 256],
+    }
+
+    impl Source<Mono<f32>> for Silence {
+        fn supply(&mut self, requested_frames: usize) -> Option<&[f32]> {
+            let frames = requested_frames.min(self.remaining).min(self.buffer.len());
+            self.remaining -= frames;
+            Some(&self.buffer[..frames])
+        }
+    }
+
+    #[test]
+    fn output_counts_input() {
+        let mut soxr = Soxr::<Mono<f32>>::new(44100.0, 48000.0).unwrap();
+        let mut source = Silence { remaining: 1000, buffer: [0.0; 256] };
+
+        let mut output = [0.0f32; 4096];
+        let produced = soxr.output(&mut source, &mut output).unwrap();
+
+        assert_eq!(soxr.total_input_frames(), 1000);
+        assert_eq!(produced, soxr.expected_output_frames());
+    }
+
+    #[test]
+    fn channel_count_can_be_changed() {
+        let mut soxr = Soxr::<DynInterleaved<f32>>::new_dynamic(44100.0, 48000.0, 1).unwrap();
+
+        let mut output = [0.0f32; 1024];
+        soxr.process(&[0.0; 256], &mut output).unwrap();
+
+        soxr.set_num_channels(2).unwrap();
+        assert_eq!(soxr.channels(), 2);
+        assert_eq!(soxr.total_input_frames(), 0);
+
+        let processed = soxr.process(&[0.0; 512], &mut output).unwrap();
+        assert_eq!(processed.input_frames, 256);
+    }
+
+    #[test]
+    fn channel_count_change_keeps_io_ratio() {
+        let mut soxr = Soxr::<DynInterleaved<f32>>::dynamic_builder(1)
+            .rates(48000.0, 48000.0)
+            .variable_rate()
+            .build()
+            .unwrap();
+
+        soxr.set_io_ratio(0.5, 0).unwrap();
+        soxr.set_num_channels(2).unwrap();
+        assert_eq!(soxr.io_ratio(), 0.5);
+
+        let input = [0.0f32; 2000];
+        let mut output = [0.0f32; 8192];
+
+        let processed = soxr.process(&input, &mut output).unwrap();
+        let mut frames = processed.output_frames;
+
+        loop {
+            let produced = soxr.drain(&mut output).unwrap();
+            if produced == 0 {
+                break;
+            }
+            frames += produced;
+        }
+
+        // 1000 stereo input frames at half the io ratio
+        assert!(frames.abs_diff(2000) < 10, "{} output frames", frames);
+    }
+
+    #[test]
+    fn missing_rates_are_rejected() {
+        let result = Soxr::<Mono<f32>>::builder().build();
+        assert!(matches!(result, Err(Error::InvalidRate(_))));
+
+        let result = Soxr::<Mono<f32>>::builder().rates(44100.0, f64::NAN).build();
+        assert!(matches!(result, Err(Error::InvalidRate(_))));
+    }
+
+    #[test]
+    fn zero_channels_is_rejected() {
+        let result = Soxr::<DynInterleaved<f32>>::new_dynamic(44100.0, 48000.0, 0);
+        assert!(matches!(result, Err(Error::InvalidChannelCount(_))));
+    }
+
+    /// Resample an impulse at `position` from 44.1k to 48k, returning the
+    /// output frame with the largest magnitude
+    fn impulse_peak(position: usize) -> usize {
+        let mut soxr = Soxr::<Mono<f32>>::new(44100.0, 48000.0).unwrap();
+
+        let mut input = [0.0f32; 2048];
+        input[position] = 1.0;
+
+        let mut output = [0.0f32; 4096];
+        let mut len = 0;
+
+        let processed = soxr.process(&input, &mut output).unwrap();
+        assert_eq!(processed.input_frames, input.len());
+        len += processed.output_frames;
+
+        loop {
+            let produced = soxr.drain(&mut output[len..]).unwrap();
+            if produced == 0 {
+                break;
+            }
+            len += produced;
+        }
+
+        output[..len].iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+            .map(|(index, _)| index)
+            .unwrap()
+    }
+
+    #[test]
+    fn output_is_aligned_with_input() {
+        // libsoxr compensates for filter delay, so an impulse lands at its
+        // input position scaled by the rate ratio
+        for position in [0, 100, 1000] {
+            let expected = libm::round(position as f64 * 48000.0 / 44100.0) as usize;
+            assert_eq!(impulse_peak(position), expected);
+        }
+    }
 }
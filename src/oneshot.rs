@@ -0,0 +1,120 @@
+use core::ffi::c_uint;
+
+use libsoxr_sys as sys;
+
+use crate::error::{self, Error};
+use crate::format::{FixedChannels, IoFormat};
+use crate::params::{IoSpec, QualitySpec, RuntimeSpec};
+use crate::Processed;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use crate::format::FrameFormat;
+
+/// Resample a whole in-memory clip in one call using `soxr_oneshot`.
+///
+/// Output is written to the start of `output`, which should be large enough
+/// to hold `input_len * output_rate / input_rate` frames, rounded up.
+pub fn oneshot<Format>(
+    input_rate: f64,
+    output_rate: f64,
+    input: &Format::Input<'_>,
+    output: &mut Format::Output<'_>,
+    quality: QualitySpec,
+) -> Result<Processed, Error>
+    where Format: IoFormat, Format::Channels: FixedChannels
+{
+    quality.validate()?;
+
+    let channels = Format::Channels::CHANNELS;
+
+    let io = sys::soxr_io_spec {
+        itype: Format::datatype(),
+        otype: Format::datatype(),
+        ..*IoSpec::default().as_raw()
+    };
+
+    let raw_channels = c_uint::try_from(channels)
+        .map_err(|_| error::CHANNEL_COUNT_TOO_LARGE)?;
+
+    let input_len = Format::input_len(input, channels);
+    let output_len = Format::output_len(output, channels);
+
+    let mut input_consumed = 0;
+    let mut output_produced = 0;
+
+    unsafe {
+        let input_ptr = Format::input_ptr(input);
+        let output_ptr = Format::output_ptr(output);
+
+        Error::check(sys::soxr_oneshot(
+            input_rate,
+            output_rate,
+            raw_channels,
+            input_ptr,
+            input_len,
+            &mut input_consumed,
+            output_ptr,
+            output_len,
+            &mut output_produced,
+            &io,
+            quality.as_raw(),
+            RuntimeSpec::default().as_raw(),
+        ))?;
+    }
+
+    Ok(Processed {
+        input_frames: input_consumed,
+        output_frames: output_produced,
+        // soxr_oneshot does not report clips
+        clips: 0,
+    })
+}
+
+/// Resample a whole in-memory clip in one call using `soxr_oneshot`,
+/// returning a correctly sized `Vec` of output frames
+#[cfg(feature = "alloc")]
+pub fn oneshot_vec<Format>(
+    input_rate: f64,
+    output_rate: f64,
+    input: &[Format::Frame],
+    quality: QualitySpec,
+) -> Result<Vec<Format::Frame>, Error>
+    where Format: FrameFormat, Format::Channels: FixedChannels
+{
+    let output_len = libm::ceil(input.len() as f64 * output_rate / input_rate) as usize;
+
+    let mut output = alloc::vec![bytemuck::Zeroable::zeroed(); output_len];
+
+    let processed = oneshot::<Format>(
+        input_rate,
+        output_rate,
+        Format::input(input),
+        Format::output(&mut output),
+        quality,
+    )?;
+
+    output.truncate(processed.output_frames);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::Mono;
+    use crate::params::QualitySpec;
+    use crate::Error;
+
+    use super::oneshot;
+
+    #[test]
+    fn invalid_quality_is_rejected() {
+        let mut quality = QualitySpec::default();
+        quality.set_passband_end(1.5);
+
+        let mut output = [0.0f32; 16];
+        let result = oneshot::<Mono<f32>>(44100.0, 48000.0, &[0.0; 8], &mut output, quality);
+        assert!(matches!(result, Err(Error::InvalidParameter(_))));
+    }
+}
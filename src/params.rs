@@ -1,13 +1,22 @@
 use core::ffi::{c_uint, c_ulong};
+use core::fmt::{self, Display};
+use core::ptr::null_mut;
+use core::str::FromStr;
 
 use libsoxr_sys as sys;
 
+use crate::error::{self, Error};
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "QualitySpecRepr", into = "QualitySpecRepr"))]
 pub struct QualitySpec {
     raw: sys::soxr_quality_spec,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 #[repr(u8)]
 pub enum QualityRecipe {
     /// `SOXR_QQ` - 'Quick' cubic interpolation.
@@ -51,6 +60,8 @@ impl Default for QualityRecipe {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 #[repr(u8)]
 pub enum Rolloff {
     /// <= 0.01 dB
@@ -67,8 +78,43 @@ impl Default for Rolloff {
     }
 }
 
+/// Filter phase response, between 0 (minimum phase) and 100 (maximum phase)
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct PhaseResponse(f64);
+
+impl PhaseResponse {
+    /// Minimum phase, no pre-ringing
+    pub const MINIMUM: Self = PhaseResponse(0.0);
+    /// Intermediate phase
+    pub const INTERMEDIATE: Self = PhaseResponse(25.0);
+    /// Linear phase, the default
+    pub const LINEAR: Self = PhaseResponse(50.0);
+    /// Maximum phase
+    pub const MAXIMUM: Self = PhaseResponse(100.0);
+
+    /// Construct a phase response, which must be between 0 and 100
+    pub fn new(value: f64) -> Result<Self, Error> {
+        if (0.0..=100.0).contains(&value) {
+            Ok(PhaseResponse(value))
+        } else {
+            Err(error::INVALID_PHASE_RESPONSE)
+        }
+    }
+
+    pub const fn value(self) -> f64 {
+        self.0
+    }
+}
+
+impl Default for PhaseResponse {
+    fn default() -> Self {
+        PhaseResponse::LINEAR
+    }
+}
+
 bitflags::bitflags! {
     #[derive(Debug, Default, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct QualityFlags: u8 {
         /// `SOXR_HI_PREC_CLOCK` - Increase `irrational' ratio accuracy.
         const HighPrecisionClock = 8;
@@ -167,6 +213,91 @@ impl QualitySpec {
     }
 
 
+    /// Rolloff, as passed to [`QualitySpec::configure`]
+    pub fn rolloff(&self) -> Rolloff {
+        match self.raw.flags & 3 {
+            1 => Rolloff::Medium,
+            2 => Rolloff::None,
+            _ => Rolloff::Small,
+        }
+    }
+
+    /// Flags, as passed to [`QualitySpec::configure`], including any set by
+    /// the recipe. Flags the recipe reserves for libsoxr's internal use are
+    /// not included.
+    pub fn flags(&self) -> QualityFlags {
+        QualityFlags::from_bits_truncate(self.raw.flags as u8)
+    }
+
+    /// Bits of the raw flags not covered by `rolloff` or `flags`
+    #[cfg(feature = "serde")]
+    fn internal_flags(&self) -> c_ulong {
+        self.raw.flags & !(3 | QualityFlags::all().bits() as c_ulong)
+    }
+
+
+    /// Set conversion precision, which must be between 0 and
+    /// [`MAX_PRECISION`] bits
+    pub fn try_set_precision(&mut self, precision: f64) -> Result<(), Error> {
+        check_precision(precision)?;
+        self.set_precision(precision);
+        Ok(())
+    }
+
+    /// Chainable convenience method to set validated conversion precision
+    pub fn try_with_precision(mut self, precision: f64) -> Result<Self, Error> {
+        self.try_set_precision(precision)?;
+        Ok(self)
+    }
+
+
+    /// Set phase response from validated [`PhaseResponse`]
+    pub fn set_phase(&mut self, phase: PhaseResponse) {
+        self.set_phase_response(phase.value());
+    }
+
+    /// Chainable convenience method to set phase response from validated
+    /// [`PhaseResponse`]
+    pub fn with_phase(mut self, phase: PhaseResponse) -> Self {
+        self.set_phase(phase);
+        self
+    }
+
+
+    /// Set passband end and stopband begin together. Passband end must be
+    /// greater than 0 and at most 1, and below stopband begin.
+    pub fn try_set_bandwidth(&mut self, passband_end: f64, stopband_begin: f64)
+        -> Result<(), Error>
+    {
+        check_bandwidth(passband_end, stopband_begin)?;
+        self.set_passband_end(passband_end);
+        self.set_stopband_begin(stopband_begin);
+        Ok(())
+    }
+
+    /// Chainable convenience method to set validated passband end and
+    /// stopband begin
+    pub fn try_with_bandwidth(mut self, passband_end: f64, stopband_begin: f64)
+        -> Result<Self, Error>
+    {
+        self.try_set_bandwidth(passband_end, stopband_begin)?;
+        Ok(self)
+    }
+
+
+    /// Check all parameters are within range, returning a descriptive error
+    /// if not. Called when creating a resampler.
+    pub fn validate(&self) -> Result<(), Error> {
+        if !self.raw.e.is_null() {
+            return Err(unsafe { Error::from_raw(self.raw.e as sys::soxr_error_t) });
+        }
+
+        check_precision(self.precision())?;
+        PhaseResponse::new(self.phase_response())?;
+        check_bandwidth(self.passband_end(), self.stopband_begin())
+    }
+
+
     pub const fn as_raw(&self) -> &sys::soxr_quality_spec {
         &self.raw
     }
@@ -176,17 +307,181 @@ impl QualitySpec {
     }
 }
 
+/// Maximum conversion precision supported by libsoxr, in bits
+pub const MAX_PRECISION: f64 = 33.0;
+
+fn check_precision(precision: f64) -> Result<(), Error> {
+    if (0.0..=MAX_PRECISION).contains(&precision) {
+        Ok(())
+    } else {
+        Err(error::INVALID_PRECISION)
+    }
+}
+
+fn check_bandwidth(passband_end: f64, stopband_begin: f64) -> Result<(), Error> {
+    if !(passband_end > 0.0 && passband_end <= 1.0) {
+        Err(error::INVALID_PASSBAND)
+    } else if stopband_begin <= passband_end || stopband_begin.is_nan() {
+        Err(error::PASSBAND_NOT_BELOW_STOPBAND)
+    } else {
+        Ok(())
+    }
+}
+
 impl Default for QualitySpec {
     fn default() -> Self {
         QualitySpec::new(QualityRecipe::high())
     }
 }
 
+/// Quality recipes selectable with SoX `rate` options, in order of
+/// preference when formatting
+const SOX_RECIPES: [(char, &str, QualityRecipe); 5] = [
+    ('h', "hq", QualityRecipe::Bits20),
+    ('v', "vhq", QualityRecipe::Bits28),
+    ('m', "mq", QualityRecipe::Medium),
+    ('l', "lq", QualityRecipe::Low),
+    ('q', "qq", QualityRecipe::Quick),
+];
+
+/// Passband end selected by SoX's `-s` steep filter option
+const STEEP_PASSBAND_END: f64 = 0.99;
+
+/// Parses SoX `rate` effect options, eg. `-v -s -I -b 90 -p 45 -a`, or a
+/// preset name: `vhq`, `hq`, `mq`, `lq` or `qq`. Options may be combined as
+/// in SoX, eg. `-vsI`.
+impl FromStr for QualitySpec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let mut recipe = QualityRecipe::default();
+        let mut phase = None;
+        let mut passband_end = None;
+        let mut allow_aliasing = false;
+
+        let mut tokens = s.split_whitespace();
+
+        while let Some(token) = tokens.next() {
+            if let Some(&(_, _, preset)) = SOX_RECIPES.iter().find(|(_, name, _)| *name == token) {
+                recipe = preset;
+                continue;
+            }
+
+            let Some(options) = token.strip_prefix('-') else {
+                return Err(error::INVALID_QUALITY_OPTION);
+            };
+
+            for (index, option) in options.char_indices() {
+                if let Some(&(_, _, preset)) = SOX_RECIPES.iter().find(|(flag, _, _)| *flag == option) {
+                    recipe = preset;
+                    continue;
+                }
+
+                match option {
+                    'M' => phase = Some(PhaseResponse::MINIMUM),
+                    'I' => phase = Some(PhaseResponse::INTERMEDIATE),
+                    'L' => phase = Some(PhaseResponse::LINEAR),
+                    's' => passband_end = Some(STEEP_PASSBAND_END),
+                    'a' => allow_aliasing = true,
+                    'b' | 'p' => {
+                        // value is either the rest of this token or the next
+                        let rest = &options[index + option.len_utf8()..];
+                        let value = if rest.is_empty() { tokens.next() } else { Some(rest) };
+                        let value = value.ok_or(error::MISSING_QUALITY_VALUE)?;
+                        let value = value.parse::<f64>()
+                            .map_err(|_| error::INVALID_QUALITY_VALUE)?;
+
+                        if option == 'b' {
+                            passband_end = Some(value / 100.0);
+                        } else {
+                            phase = Some(PhaseResponse::new(value)?);
+                        }
+
+                        break;
+                    }
+                    _ => return Err(error::INVALID_QUALITY_OPTION),
+                }
+            }
+        }
+
+        let mut spec = QualitySpec::new(recipe);
+
+        if let Some(phase) = phase {
+            spec.set_phase(phase);
+        }
+
+        if let Some(passband_end) = passband_end {
+            spec.set_passband_end(passband_end);
+        }
+
+        if allow_aliasing {
+            spec.set_stopband_begin(2.0 - spec.passband_end());
+        }
+
+        spec.validate()?;
+        Ok(spec)
+    }
+}
+
+/// Formats as SoX `rate` effect options which parse back to the same spec.
+/// Settings SoX cannot express, such as flags, rolloff and precisions other
+/// than those of the SoX recipes, are omitted.
+impl Display for QualitySpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let candidates = SOX_RECIPES.map(|(flag, _, recipe)| (flag, QualitySpec::new(recipe)));
+
+        // prefer a recipe matching both precision and bandwidth
+        let (flag, base) = candidates.iter()
+            .find(|(_, base)| {
+                base.precision() == self.precision()
+                    && base.passband_end() == self.passband_end()
+            })
+            .or_else(|| candidates.iter().find(|(_, base)| base.precision() == self.precision()))
+            .unwrap_or(&candidates[0]);
+
+        write!(f, "-{}", flag)?;
+
+        let phase = self.phase_response();
+
+        if phase != base.phase_response() {
+            match PhaseResponse::new(phase) {
+                Ok(PhaseResponse::MINIMUM) => f.write_str(" -M")?,
+                Ok(PhaseResponse::INTERMEDIATE) => f.write_str(" -I")?,
+                Ok(PhaseResponse::LINEAR) => f.write_str(" -L")?,
+                _ => write!(f, " -p {}", phase)?,
+            }
+        }
+
+        let passband_end = self.passband_end();
+
+        if passband_end == STEEP_PASSBAND_END {
+            f.write_str(" -s")?;
+        } else if passband_end != base.passband_end() {
+            // round away float error so decimal percentages print cleanly
+            let percent = libm::round(passband_end * 1e11) / 1e9;
+            write!(f, " -b {}", percent)?;
+        }
+
+        if self.stopband_begin() != base.stopband_begin()
+            && self.stopband_begin() == 2.0 - passband_end
+        {
+            f.write_str(" -a")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "RuntimeSpecRepr", into = "RuntimeSpecRepr"))]
 pub struct RuntimeSpec {
     raw: sys::soxr_runtime_spec,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 #[repr(u8)]
 pub enum Interpolation {
     /// Auto select coef. interpolation.
@@ -240,7 +535,7 @@ impl RuntimeSpec {
 
     /// For `Interpolation::Auto`, typically 400
     pub fn coef_size_kbytes(&self) -> c_uint {
-        self.raw.log2_large_dft_size
+        self.raw.coef_size_kbytes
     }
 
     /// Set `coef_size_kbytes`
@@ -314,3 +609,304 @@ impl Default for RuntimeSpec {
         Self::new(1)
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct IoSpec {
+    raw: sys::soxr_io_spec,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Dither {
+    /// `SOXR_TPDF` - Applicable only if otype is integer.
+    #[default]
+    Tpdf = 0,
+    /// `SOXR_NO_DITHER` - Disable the above.
+    None = 8,
+}
+
+impl IoSpec {
+    /// Construct a new `IoSpec` with the given scale and dither mode
+    pub fn new(scale: f64, dither: Dither) -> Self {
+        // itype and otype are filled in from the resampler's formats
+        // when it is created
+        unsafe {
+            Self::from_raw(sys::soxr_io_spec {
+                itype: 0,
+                otype: 0,
+                scale,
+                e: null_mut(),
+                flags: dither as u8 as c_ulong,
+            })
+        }
+    }
+
+
+    /// Linear gain to apply during conversion; typically 1.0. For a gain
+    /// of `g` dB, use `10^(g/20)`
+    pub fn scale(&self) -> f64 {
+        self.raw.scale
+    }
+
+    /// Set scale
+    pub fn set_scale(&mut self, scale: f64) {
+        self.raw.scale = scale;
+    }
+
+    /// Chainable convenience method to set scale
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.set_scale(scale);
+        self
+    }
+
+
+    /// Dither applied when output is integer; typically TPDF
+    pub fn dither(&self) -> Dither {
+        if self.raw.flags & Dither::None as u8 as c_ulong != 0 {
+            Dither::None
+        } else {
+            Dither::Tpdf
+        }
+    }
+
+    /// Set dither
+    pub fn set_dither(&mut self, dither: Dither) {
+        self.raw.flags &= !(Dither::None as u8 as c_ulong);
+        self.raw.flags |= dither as u8 as c_ulong;
+    }
+
+    /// Chainable convenience method to set dither
+    pub fn with_dither(mut self, dither: Dither) -> Self {
+        self.set_dither(dither);
+        self
+    }
+
+
+    pub const fn as_raw(&self) -> &sys::soxr_io_spec {
+        &self.raw
+    }
+
+    /// # Safety
+    ///
+    /// `raw.e` must be null or point to a static libsoxr error message.
+    /// `itype` and `otype` are overwritten from the resampler's formats when
+    /// it is created.
+    pub const unsafe fn from_raw(raw: sys::soxr_io_spec) -> Self {
+        IoSpec { raw }
+    }
+}
+
+impl Default for IoSpec {
+    fn default() -> Self {
+        Self::new(1.0, Dither::default())
+    }
+}
+
+/// Serialized form of [`QualitySpec`]
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "QualitySpec", default)]
+struct QualitySpecRepr {
+    precision: f64,
+    phase_response: f64,
+    passband_end: f64,
+    stopband_begin: f64,
+    rolloff: Rolloff,
+    flags: QualityFlags,
+    /// Flags reserved for libsoxr's internal use; not serialized, so taken
+    /// from the default spec when deserializing
+    #[serde(skip)]
+    internal_flags: c_ulong,
+}
+
+#[cfg(feature = "serde")]
+impl Default for QualitySpecRepr {
+    fn default() -> Self {
+        QualitySpec::default().into()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<QualitySpec> for QualitySpecRepr {
+    fn from(spec: QualitySpec) -> Self {
+        QualitySpecRepr {
+            precision: spec.precision(),
+            phase_response: spec.phase_response(),
+            passband_end: spec.passband_end(),
+            stopband_begin: spec.stopband_begin(),
+            rolloff: spec.rolloff(),
+            flags: spec.flags(),
+            internal_flags: spec.internal_flags(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<QualitySpecRepr> for QualitySpec {
+    type Error = Error;
+
+    fn try_from(repr: QualitySpecRepr) -> Result<Self, Error> {
+        let flags = (repr.flags.bits() | repr.rolloff as u8) as c_ulong | repr.internal_flags;
+
+        let spec = unsafe {
+            QualitySpec::from_raw(sys::soxr_quality_spec {
+                precision: repr.precision,
+                phase_response: repr.phase_response,
+                passband_end: repr.passband_end,
+                stopband_begin: repr.stopband_begin,
+                e: null_mut(),
+                flags,
+            })
+        };
+
+        spec.validate()?;
+        Ok(spec)
+    }
+}
+
+/// Serialized form of [`RuntimeSpec`]
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "RuntimeSpec", default)]
+struct RuntimeSpecRepr {
+    log2_min_dft_size: c_uint,
+    log2_large_dft_size: c_uint,
+    coef_size_kbytes: c_uint,
+    num_threads: c_uint,
+    interpolation: Interpolation,
+}
+
+#[cfg(feature = "serde")]
+impl Default for RuntimeSpecRepr {
+    fn default() -> Self {
+        RuntimeSpec::default().into()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<RuntimeSpec> for RuntimeSpecRepr {
+    fn from(spec: RuntimeSpec) -> Self {
+        RuntimeSpecRepr {
+            log2_min_dft_size: spec.log2_min_dft_size(),
+            log2_large_dft_size: spec.log2_large_dft_size(),
+            coef_size_kbytes: spec.coef_size_kbytes(),
+            num_threads: spec.num_threads(),
+            interpolation: spec.interpolation(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<RuntimeSpecRepr> for RuntimeSpec {
+    fn from(repr: RuntimeSpecRepr) -> Self {
+        RuntimeSpec::new(repr.num_threads)
+            .with_log2_min_dft_size(repr.log2_min_dft_size)
+            .with_log2_large_dft_size(repr.log2_large_dft_size)
+            .with_coef_size_kbytes(repr.coef_size_kbytes)
+            .with_interpolation(repr.interpolation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::ToString;
+
+    use super::{PhaseResponse, QualityRecipe, QualitySpec};
+
+    fn assert_same(a: &QualitySpec, b: &QualitySpec) {
+        assert_eq!(a.precision(), b.precision());
+        assert_eq!(a.phase_response(), b.phase_response());
+        assert_eq!(a.passband_end(), b.passband_end());
+        assert_eq!(a.stopband_begin(), b.stopband_begin());
+    }
+
+    #[test]
+    fn parse_sox_options() {
+        let spec = "-v -s -I -b 90 -p 45 -a".parse::<QualitySpec>().unwrap();
+
+        // later options override earlier ones, as in SoX
+        assert_eq!(spec.precision(), QualitySpec::new(QualityRecipe::Bits28).precision());
+        assert_eq!(spec.phase_response(), 45.0);
+        assert_eq!(spec.passband_end(), 0.9);
+        assert_eq!(spec.stopband_begin(), 2.0 - 0.9);
+    }
+
+    #[test]
+    fn parse_combined_options() {
+        let spec = "-vsI".parse::<QualitySpec>().unwrap();
+
+        assert_eq!(spec.precision(), QualitySpec::new(QualityRecipe::Bits28).precision());
+        assert_eq!(spec.phase_response(), PhaseResponse::INTERMEDIATE.value());
+        assert_eq!(spec.passband_end(), 0.99);
+    }
+
+    #[test]
+    fn parse_presets() {
+        let presets = [
+            ("vhq", QualityRecipe::Bits28),
+            ("hq", QualityRecipe::Bits20),
+            ("mq", QualityRecipe::Medium),
+            ("lq", QualityRecipe::Low),
+            ("qq", QualityRecipe::Quick),
+        ];
+
+        for (name, recipe) in presets {
+            assert_same(&name.parse().unwrap(), &QualitySpec::new(recipe));
+        }
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!("-x".parse::<QualitySpec>().is_err());
+        assert!("-b".parse::<QualitySpec>().is_err());
+        assert!("-b wide".parse::<QualitySpec>().is_err());
+        assert!("-p 101".parse::<QualitySpec>().is_err());
+    }
+
+    #[test]
+    fn display_parses_back() {
+        for options in ["-h", "-v", "-m -M", "-v -s -I", "-l -p 45 -b 90 -a", "-q -L"] {
+            let spec = options.parse::<QualitySpec>().unwrap();
+            let formatted = spec.to_string();
+            assert_same(&formatted.parse().unwrap(), &spec);
+        }
+
+        let spec = "-v -s -I -b 90 -p 45 -a".parse::<QualitySpec>().unwrap();
+        assert_eq!(spec.to_string(), "-v -p 45 -b 90 -a");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        use super::{QualityFlags, Rolloff};
+
+        let recipes = [
+            QualityRecipe::Quick,
+            QualityRecipe::Low,
+            QualityRecipe::Medium,
+            QualityRecipe::Bits16,
+            QualityRecipe::Bits20,
+            QualityRecipe::Bits24,
+            QualityRecipe::Bits28,
+            QualityRecipe::Bits32,
+        ];
+
+        let flags = QualityFlags::HighPrecisionClock | QualityFlags::VariableRate;
+
+        for recipe in recipes {
+            let spec = QualitySpec::configure(recipe, Rolloff::Medium, flags)
+                .with_passband_end(0.9);
+
+            let json = serde_json::to_string(&spec).unwrap();
+            assert!(!json.contains("internal_flags"));
+
+            let round_trip = serde_json::from_str::<QualitySpec>(&json).unwrap();
+
+            assert!(matches!(round_trip.rolloff(), Rolloff::Medium));
+            assert_eq!(round_trip.flags().bits(), spec.flags().bits());
+            assert_same(&round_trip, &spec);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use core::ffi::c_void;
+
+use libsoxr_sys as sys;
+
+use crate::format::IoFormat;
+
+/// Supplies input to a resampler in pull mode. See [`Soxr::output`].
+///
+/// [`Soxr::output`]: crate::Soxr::output
+pub trait Source<F: IoFormat> {
+    /// Supply up to `requested_frames` frames of input. The returned buffer
+    /// must stay valid until the next call to `supply`. Any frames beyond
+    /// `requested_frames` are ignored.
+    ///
+    /// Return `None` or an empty buffer to signal the end of the input
+    /// stream, after which the resampler is drained.
+    fn supply(&mut self, requested_frames: usize) -> Option<&F::Input<'_>>;
+}
+
+impl<F: IoFormat, S: Source<F> + ?Sized> Source<F> for &mut S {
+    fn supply(&mut self, requested_frames: usize) -> Option<&F::Input<'_>> {
+        (**self).supply(requested_frames)
+    }
+}
+
+/// State passed through `soxr_set_input_fn` to [`input_fn`]
+pub(crate) struct InputFnState<'a, S: ?Sized> {
+    pub source: &'a mut S,
+    pub channels: usize,
+    /// Frames handed to the resampler so far
+    pub frames_supplied: usize,
+}
+
+/// `soxr_input_fn_t` trampoline. `state` must point to an `InputFnState`.
+pub(crate) unsafe extern "C" fn input_fn<F, S>(
+    state: *mut c_void,
+    data: *mut sys::soxr_in_t,
+    requested_len: usize,
+) -> usize
+    where F: IoFormat, S: Source<F> + ?Sized
+{
+    let state = &mut *state.cast::<InputFnState<S>>();
+
+    match state.source.supply(requested_len) {
+        Some(input) => {
+            *data = F::input_ptr(input);
+            let frames = F::input_len(input, state.channels).min(requested_len);
+            state.frames_supplied += frames;
+            frames
+        }
+        // returning zero while leaving data untouched signals end of input
+        None => 0,
+    }
+}
@@ -0,0 +1,285 @@
+use alloc::vec::Vec;
+
+use bytemuck::Zeroable;
+
+use crate::format::{FrameFormat, Sample};
+use crate::{Error, Soxr};
+
+/// Minimum number of output frames to make room for on each call to
+/// `Soxr::process`
+const MIN_OUTPUT_CHUNK: usize = 1024;
+
+/// Streaming resampler that owns its buffers. Input may be pushed in chunks
+/// of any size; input not yet consumed by the resampler is buffered
+/// internally, and the final drain is performed by `finish`.
+pub struct ResamplerStream<In, Out = In>
+    where In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{
+    soxr: Soxr<In, Out>,
+    pending: Vec<In::Frame>,
+    output: Vec<Out::Frame>,
+    /// Output flushed by the last `reset_with_crossfade`, faded out over
+    /// the start of new output
+    tail: Vec<Out::Frame>,
+    /// Number of frames of `tail` crossfaded so far
+    crossfade_pos: usize,
+}
+
+impl<In, Out> ResamplerStream<In, Out>
+    where In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{
+    /// Wrap an existing resampler
+    pub fn new(soxr: Soxr<In, Out>) -> Self {
+        ResamplerStream {
+            soxr,
+            pending: Vec::new(),
+            output: Vec::new(),
+            tail: Vec::new(),
+            crossfade_pos: 0,
+        }
+    }
+
+    pub fn soxr(&self) -> &Soxr<In, Out> {
+        &self.soxr
+    }
+
+    pub fn soxr_mut(&mut self) -> &mut Soxr<In, Out> {
+        &mut self.soxr
+    }
+
+    pub fn into_inner(self) -> Soxr<In, Out> {
+        self.soxr
+    }
+
+    /// Number of input frames buffered, waiting to be consumed by the
+    /// resampler
+    pub fn pending_frames(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Push input frames, returning output produced. The returned slice is
+    /// valid until the next call on this stream.
+    pub fn push(&mut self, input: &[In::Frame]) -> Result<&[Out::Frame], Error> {
+        let mut output = core::mem::take(&mut self.output);
+        output.clear();
+        let result = self.push_into(input, &mut output);
+        self.output = output;
+        result?;
+        Ok(&self.output)
+    }
+
+    /// Push input frames, appending output produced to `output`
+    pub fn push_into(&mut self, input: &[In::Frame], output: &mut Vec<Out::Frame>)
+        -> Result<(), Error>
+    {
+        let start = output.len();
+
+        if self.pending.is_empty() {
+            let consumed = process_into(&mut self.soxr, input, output)?;
+            self.pending.extend_from_slice(&input[consumed..]);
+        } else {
+            self.pending.extend_from_slice(input);
+            let consumed = process_into(&mut self.soxr, &self.pending, output)?;
+            self.pending.drain(..consumed);
+        }
+
+        self.apply_crossfade(&mut output[start..]);
+
+        Ok(())
+    }
+
+    /// Indicate the end of input, returning all remaining output. The
+    /// returned slice is valid until the next call on this stream.
+    pub fn finish(&mut self) -> Result<&[Out::Frame], Error> {
+        let mut output = core::mem::take(&mut self.output);
+        output.clear();
+        let result = self.finish_into(&mut output);
+        self.output = output;
+        result?;
+        Ok(&self.output)
+    }
+
+    /// Indicate the end of input, appending all remaining output to
+    /// `output`. Calling this again before `clear` produces no more output.
+    pub fn finish_into(&mut self, output: &mut Vec<Out::Frame>) -> Result<(), Error> {
+        let output_start = output.len();
+
+        // with nothing pending there is no input left to give the resampler,
+        // which may also have been drained already by an earlier `finish`
+        if !self.pending.is_empty() {
+            let consumed = process_into(&mut self.soxr, &self.pending, output)?;
+            self.pending.drain(..consumed);
+        }
+
+        loop {
+            let start = output.len();
+            output.resize(start + MIN_OUTPUT_CHUNK, Zeroable::zeroed());
+
+            let result = self.soxr.drain(Out::output(&mut output[start..]));
+            let produced = *result.as_ref().unwrap_or(&0);
+            output.truncate(start + produced);
+            result?;
+
+            if produced == 0 {
+                break;
+            }
+        }
+
+        // anything the resampler would not accept is dropped
+        self.pending.clear();
+
+        // finish any crossfade in progress against silence
+        let remaining = self.tail.len() - self.crossfade_pos;
+        if output.len() - output_start < remaining {
+            output.resize(output_start + remaining, Zeroable::zeroed());
+        }
+
+        self.apply_crossfade(&mut output[output_start..]);
+
+        Ok(())
+    }
+
+    /// Reset the resampler and discard buffered input, ready to start a new
+    /// stream
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.pending.clear();
+        self.tail.clear();
+        self.crossfade_pos = 0;
+        self.soxr.clear()
+    }
+
+    /// Reset the resampler for a discontinuity in the input, such as a
+    /// seek. Buffered input is flushed through the old filter state, and
+    /// the resulting output is crossfaded into the first `frames` frames of
+    /// output after the reset, rather than being returned directly. May
+    /// also be called after `finish`, to start a new stream.
+    pub fn reset_with_crossfade(&mut self, frames: usize) -> Result<(), Error> {
+        let mut tail = Vec::new();
+        self.finish_into(&mut tail)?;
+
+        // tail beyond the crossfade is dropped, and a tail shorter than it
+        // has already decayed to silence. Nothing is flushed if the stream
+        // was already finished, in which case there is nothing to fade out.
+        if !tail.is_empty() {
+            tail.resize(frames, Zeroable::zeroed());
+        }
+
+        self.pending.clear();
+        self.tail = tail;
+        self.crossfade_pos = 0;
+        self.soxr.clear()
+    }
+
+    /// Number of output frames remaining in the crossfade started by
+    /// `reset_with_crossfade`
+    pub fn crossfade_remaining(&self) -> usize {
+        self.tail.len() - self.crossfade_pos
+    }
+
+    /// Mix the crossfade tail into the start of newly produced output
+    fn apply_crossfade(&mut self, output: &mut [Out::Frame]) {
+        let len = self.tail.len();
+        let frames = output.len().min(len - self.crossfade_pos);
+
+        for (frame, old) in output[..frames].iter_mut().zip(&self.tail[self.crossfade_pos..]) {
+            self.crossfade_pos += 1;
+            let gain = self.crossfade_pos as f64 / (len + 1) as f64;
+
+            let new = bytemuck::cast_slice_mut::<_, Out::Sample>(core::slice::from_mut(frame));
+            let old = bytemuck::cast_slice::<_, Out::Sample>(core::slice::from_ref(old));
+
+            for (new, old) in new.iter_mut().zip(old) {
+                let mixed = old.to_f64() * (1.0 - gain) + new.to_f64() * gain;
+                *new = Sample::from_f64(mixed);
+            }
+        }
+
+        if self.crossfade_pos == len {
+            self.tail.clear();
+            self.crossfade_pos = 0;
+        }
+    }
+}
+
+/// Process as much of `input` as the resampler accepts, appending output to
+/// `output`. Returns number of input frames consumed.
+fn process_into<In, Out>(
+    soxr: &mut Soxr<In, Out>,
+    input: &[In::Frame],
+    output: &mut Vec<Out::Frame>,
+) -> Result<usize, Error>
+    where In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{
+    let mut consumed = 0;
+
+    loop {
+        let chunk = (input.len() - consumed).max(MIN_OUTPUT_CHUNK);
+        let start = output.len();
+        output.resize(start + chunk, Zeroable::zeroed());
+
+        let result = soxr.process(
+            In::input(&input[consumed..]),
+            Out::output(&mut output[start..]),
+        );
+
+        let produced = result.as_ref().map(|p| p.output_frames).unwrap_or(0);
+        output.truncate(start + produced);
+        let processed = result?;
+
+        consumed += processed.input_frames;
+
+        let made_progress = processed.input_frames > 0 || processed.output_frames > 0;
+        let output_full = processed.output_frames == chunk;
+
+        if !made_progress || (consumed == input.len() && !output_full) {
+            return Ok(consumed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::Mono;
+    use crate::Soxr;
+
+    use super::ResamplerStream;
+
+    fn stream() -> ResamplerStream<Mono<f32>> {
+        ResamplerStream::new(Soxr::new(44100.0, 48000.0).unwrap())
+    }
+
+    #[test]
+    fn finish_twice() {
+        let mut stream = stream();
+        stream.push(&[0.5; 1000]).unwrap();
+
+        assert!(!stream.finish().unwrap().is_empty());
+        assert!(stream.finish().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reset_after_finish() {
+        let mut stream = stream();
+        stream.push(&[0.5; 1000]).unwrap();
+        stream.finish().unwrap();
+
+        stream.reset_with_crossfade(64).unwrap();
+        assert_eq!(stream.crossfade_remaining(), 0);
+
+        stream.push(&[0.5; 1000]).unwrap();
+        assert!(!stream.finish().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reset_mid_stream_crossfades() {
+        let mut stream = stream();
+        stream.push(&[0.5; 1000]).unwrap();
+
+        stream.reset_with_crossfade(64).unwrap();
+        assert_eq!(stream.crossfade_remaining(), 64);
+
+        stream.push(&[0.5; 1000]).unwrap();
+        stream.finish().unwrap();
+        assert_eq!(stream.crossfade_remaining(), 0);
+    }
+}
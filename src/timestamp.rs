@@ -0,0 +1,328 @@
+use core::ops::Deref;
+
+use crate::error;
+use crate::format::IoFormat;
+use crate::{Error, Processed, Soxr};
+
+/// Default threshold beyond which input timestamps are treated as
+/// discontinuous, in seconds
+pub const DEFAULT_TOLERANCE: f64 = 0.001;
+
+/// Maximum number of discontinuities which may be buffered in the resampler
+/// at once, not yet having reached the output
+pub const MAX_DISCONTINUITIES: usize = 8;
+
+/// Discontinuities in the input timeline, each as the index of the first
+/// output frame after it and that frame's presentation timestamp
+#[derive(Debug, Clone, Copy)]
+pub struct Discontinuities {
+    items: [(usize, f64); MAX_DISCONTINUITIES],
+    len: usize,
+}
+
+impl Discontinuities {
+    const fn new() -> Self {
+        Discontinuities {
+            items: [(0, 0.0); MAX_DISCONTINUITIES],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, item: (usize, f64)) -> Result<(), Error> {
+        if self.len == MAX_DISCONTINUITIES {
+            return Err(error::TOO_MANY_DISCONTINUITIES);
+        }
+
+        self.items[self.len] = item;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop_front(&mut self) -> Option<(usize, f64)> {
+        let first = self.first().copied()?;
+        self.items.copy_within(1..self.len, 0);
+        self.len -= 1;
+        Some(first)
+    }
+}
+
+impl Deref for Discontinuities {
+    type Target = [(usize, f64)];
+
+    fn deref(&self) -> &Self::Target {
+        &self.items[..self.len]
+    }
+}
+
+/// Output of a call to [`Timestamped::process`] or [`Timestamped::drain`]
+#[derive(Debug, Clone, Copy)]
+pub struct TimestampedOutput {
+    pub input_frames: usize,
+    pub output_frames: usize,
+    pub clips: usize,
+    /// Presentation timestamp of the first output frame, in seconds
+    pub pts: f64,
+    /// Discontinuities in the input timeline falling within this output,
+    /// in order. One falling on the first output frame is reflected in
+    /// `pts` instead.
+    pub discontinuities: Discontinuities,
+}
+
+/// Resampler wrapper which maps presentation timestamps of input chunks to
+/// timestamps of output chunks, accounting for data buffered in the
+/// resampler, io ratio changes and discontinuities in the input timeline.
+///
+/// Timestamps are in seconds on the input timeline. While the io ratio is
+/// slewing, output timestamps are approximated using the target ratio.
+pub struct Timestamped<In: IoFormat, Out: IoFormat = In> {
+    soxr: Soxr<In, Out>,
+    tolerance: f64,
+    /// Input frames per output frame
+    io_ratio: f64,
+    /// Expected timestamp of next input frame, `None` before first input
+    next_input_pts: Option<f64>,
+    /// Timestamp of next output frame
+    next_output_pts: f64,
+    /// Output frames produced so far
+    output_position: usize,
+    /// Pending discontinuities as output position at which each new
+    /// timeline begins, and timestamp there
+    pending: Discontinuities,
+}
+
+impl<In, Out> Timestamped<In, Out>
+    where In: IoFormat, Out: IoFormat<Channels = In::Channels>
+{
+    pub fn new(soxr: Soxr<In, Out>) -> Self {
+        Self::with_tolerance(soxr, DEFAULT_TOLERANCE)
+    }
+
+    /// Create with custom discontinuity threshold, in seconds
+    pub fn with_tolerance(soxr: Soxr<In, Out>, tolerance: f64) -> Self {
+        let io_ratio = soxr.input_rate() / soxr.output_rate();
+
+        Timestamped {
+            soxr,
+            tolerance,
+            io_ratio,
+            next_input_pts: None,
+            next_output_pts: 0.0,
+            output_position: 0,
+            pending: Discontinuities::new(),
+        }
+    }
+
+    pub fn soxr(&self) -> &Soxr<In, Out> {
+        &self.soxr
+    }
+
+    pub fn into_inner(self) -> Soxr<In, Out> {
+        self.soxr
+    }
+
+    /// Duration of one output frame on the input timeline, in seconds
+    fn output_frame_duration(&self) -> f64 {
+        self.io_ratio / self.soxr.input_rate()
+    }
+
+    /// Process input chunk with presentation timestamp `pts`. Any input not
+    /// consumed should be passed again with its timestamp advanced by the
+    /// number of frames consumed.
+    ///
+    /// Returns [`Error::InvalidState`] if a discontinuity would exceed
+    /// [`MAX_DISCONTINUITIES`] still buffered in the resampler.
+    pub fn process<'a>(
+        &mut self,
+        pts: f64,
+        input: &In::Input<'a>,
+        output: &mut Out::Output<'a>,
+    ) -> Result<TimestampedOutput, Error> {
+        match self.next_input_pts {
+            None => {
+                self.next_output_pts = pts;
+            }
+            Some(expected) if (pts - expected).abs() > self.tolerance => {
+                // output still buffered in the resampler belongs to the old
+                // timeline, new timeline begins after it
+                let buffered = libm::round(self.soxr.delay()) as usize;
+                let position = self.output_position + buffered;
+                self.pending.push((position, pts))?;
+            }
+            Some(_) => {}
+        }
+
+        let processed = self.soxr.process(input, output)?;
+
+        let input_duration = processed.input_frames as f64 / self.soxr.input_rate();
+        self.next_input_pts = Some(pts + input_duration);
+
+        Ok(self.advance(processed))
+    }
+
+    /// Drain the resampler, see [`Soxr::drain`]
+    pub fn drain<'a>(&mut self, output: &mut Out::Output<'a>)
+        -> Result<TimestampedOutput, Error>
+    {
+        let output_frames = self.soxr.drain(output)?;
+
+        Ok(self.advance(Processed {
+            input_frames: 0,
+            output_frames,
+            clips: 0,
+        }))
+    }
+
+    /// Advance output timeline over output produced
+    fn advance(&mut self, processed: Processed) -> TimestampedOutput {
+        let start = self.output_position;
+        let end = start + processed.output_frames;
+        self.output_position = end;
+
+        let mut pts = self.next_output_pts;
+        let mut discontinuities = Discontinuities::new();
+
+        // position and timestamp of the start of the last timeline within
+        // this output
+        let mut anchor = (start, pts);
+
+        while let Some(&(position, new_pts)) = self.pending.first() {
+            if position >= end {
+                break;
+            }
+
+            self.pending.pop_front();
+
+            let index = position.saturating_sub(start);
+            anchor = (start + index, new_pts);
+
+            if index == 0 {
+                pts = new_pts;
+            } else {
+                // at most as many as were pending, so always fits
+                let _ = discontinuities.push((index, new_pts));
+            }
+        }
+
+        let (anchor_position, anchor_pts) = anchor;
+        let after = (end - anchor_position) as f64;
+        self.next_output_pts = anchor_pts + after * self.output_frame_duration();
+
+        TimestampedOutput {
+            input_frames: processed.input_frames,
+            output_frames: processed.output_frames,
+            clips: processed.clips,
+            pts,
+            discontinuities,
+        }
+    }
+
+    /// Change the io ratio, see [`Soxr::set_io_ratio`]
+    pub fn set_io_ratio(&mut self, ratio: f64, slew_len: usize) -> Result<(), Error> {
+        self.soxr.set_io_ratio(ratio, slew_len)?;
+        self.io_ratio = ratio;
+        Ok(())
+    }
+
+    /// Change rates, see [`Soxr::set_rates`]
+    pub fn set_rates(&mut self, input_rate: f64, output_rate: f64, slew_len: usize)
+        -> Result<(), Error>
+    {
+        self.soxr.set_rates(input_rate, output_rate, slew_len)?;
+        self.io_ratio = input_rate / output_rate;
+        Ok(())
+    }
+
+    /// Reset the resampler and timestamp state, see [`Soxr::clear`]
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.soxr.clear()?;
+        self.next_input_pts = None;
+        self.next_output_pts = 0.0;
+        self.output_position = 0;
+        self.pending = Discontinuities::new();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::Mono;
+    use crate::Soxr;
+
+    use super::{Timestamped, TimestampedOutput};
+
+    const INPUT: [f32; 1000] = [0.0; 1000];
+
+    fn process(timestamped: &mut Timestamped<Mono<f32>>, pts: f64) -> TimestampedOutput {
+        let mut output = [0.0f32; 4096];
+        let result = timestamped.process(pts, &INPUT, &mut output).unwrap();
+        assert_eq!(result.input_frames, INPUT.len());
+        result
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn continuous_input() {
+        let mut timestamped = Timestamped::new(Soxr::new(44100.0, 48000.0).unwrap());
+
+        let first = process(&mut timestamped, 5.0);
+        assert_close(first.pts, 5.0);
+
+        let second = process(&mut timestamped, 5.0 + 1000.0 / 44100.0);
+        assert_close(second.pts, 5.0 + first.output_frames as f64 / 48000.0);
+        assert!(second.discontinuities.is_empty());
+    }
+
+    #[test]
+    fn pts_jump() {
+        let mut timestamped = Timestamped::new(Soxr::new(44100.0, 48000.0).unwrap());
+
+        let first = process(&mut timestamped, 0.0);
+        let second = process(&mut timestamped, 10.0);
+
+        let mut output = [0.0f32; 4096];
+        let drained = timestamped.drain(&mut output).unwrap();
+
+        // find where the new timeline begins, either within an output or
+        // at the start of one
+        let mut offset = 0;
+        let mut jump = None;
+
+        for output in [first, second, drained] {
+            if offset > 0 && output.pts == 10.0 {
+                jump = Some(offset);
+            }
+
+            if let Some(&(index, pts)) = output.discontinuities.first() {
+                assert_eq!(pts, 10.0);
+                jump = Some(offset + index);
+            }
+
+            offset += output.output_frames;
+        }
+
+        // output is delay compensated, so the new timeline begins once all
+        // output from the first chunk has been produced
+        let expected = crate::length::output_frames(1000, 44100.0, 48000.0);
+        let jump = jump.unwrap();
+        assert!(jump.abs_diff(expected) <= 1, "jump at {}, expected {}", jump, expected);
+    }
+
+    #[test]
+    fn io_ratio_change() {
+        let soxr = Soxr::variable_rate(48000.0, 48000.0).unwrap();
+        let mut timestamped = Timestamped::new(soxr);
+
+        process(&mut timestamped, 0.0);
+        timestamped.set_io_ratio(2.0, 0).unwrap();
+
+        let pts = 1000.0 / 48000.0;
+        let first = process(&mut timestamped, pts);
+        let second = process(&mut timestamped, pts + 1000.0 / 48000.0);
+
+        // each output frame now spans two input frames
+        assert_close(second.pts, first.pts + first.output_frames as f64 * 2.0 / 48000.0);
+    }
+}
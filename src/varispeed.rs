@@ -0,0 +1,330 @@
+use crate::format::FrameFormat;
+use crate::{Error, Processed, Soxr};
+
+/// Smallest speed applied by [`Varispeed`]. libsoxr cannot stop entirely,
+/// so lower speeds from a curve are clamped to this.
+pub const MIN_SPEED: f64 = 1e-3;
+
+/// Playback speed as a function of position in the input, in frames. A
+/// speed of `1.0` plays at the resampler's nominal rate, `0.5` at half
+/// speed, and so on.
+pub trait RateCurve {
+    fn speed_at(&mut self, position: f64) -> f64;
+
+    /// Position of the first breakpoint after `position`, if any. Blocks
+    /// processed by [`Varispeed`] end at breakpoints, so that changes in the
+    /// curve's shape take effect at exactly the right frame.
+    fn next_breakpoint(&self, _position: f64) -> Option<f64> {
+        None
+    }
+}
+
+impl<F: FnMut(f64) -> f64> RateCurve for F {
+    fn speed_at(&mut self, position: f64) -> f64 {
+        self(position)
+    }
+}
+
+/// Shape of a [`Breakpoints`] curve between one breakpoint and the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    /// Speed changes linearly
+    Linear,
+    /// Speed changes by a constant factor per frame, giving a linear change
+    /// in pitch. Both breakpoint speeds must be positive.
+    Exponential,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Breakpoint {
+    /// Position in the input, in frames
+    pub position: f64,
+    /// Speed at this position
+    pub speed: f64,
+    /// Shape of the curve from this breakpoint to the next
+    pub shape: Shape,
+}
+
+/// Rate curve interpolated between breakpoints, which must be sorted by
+/// position. The curve holds the first speed before the first breakpoint
+/// and the last speed after the last breakpoint.
+#[derive(Debug, Clone, Copy)]
+pub struct Breakpoints<'a> {
+    points: &'a [Breakpoint],
+    /// Index of segment found by the last lookup, as positions are usually
+    /// queried in increasing order
+    cursor: usize,
+}
+
+impl<'a> Breakpoints<'a> {
+    /// # Panics
+    ///
+    /// Panics if `points` is empty or not sorted by position
+    pub fn new(points: &'a [Breakpoint]) -> Self {
+        if points.is_empty() {
+            panic!("breakpoint curve must have at least one breakpoint");
+        }
+
+        if points.windows(2).any(|pair| pair[0].position > pair[1].position) {
+            panic!("breakpoints must be sorted by position");
+        }
+
+        Breakpoints { points, cursor: 0 }
+    }
+}
+
+impl RateCurve for Breakpoints<'_> {
+    fn speed_at(&mut self, position: f64) -> f64 {
+        let points = self.points;
+
+        if position <= points[0].position {
+            return points[0].speed;
+        }
+
+        let last = points[points.len() - 1];
+        if position >= last.position {
+            return last.speed;
+        }
+
+        if points[self.cursor].position > position {
+            self.cursor = 0;
+        }
+
+        while points[self.cursor + 1].position <= position {
+            self.cursor += 1;
+        }
+
+        let from = points[self.cursor];
+        let to = points[self.cursor + 1];
+        let t = (position - from.position) / (to.position - from.position);
+
+        match from.shape {
+            Shape::Linear => from.speed + (to.speed - from.speed) * t,
+            Shape::Exponential => from.speed * libm::pow(to.speed / from.speed, t),
+        }
+    }
+
+    fn next_breakpoint(&self, position: f64) -> Option<f64> {
+        let index = self.points.partition_point(|point| point.position <= position);
+        self.points.get(index).map(|point| point.position)
+    }
+}
+
+/// Variable rate resampler driven by a [`RateCurve`]. Input is processed in
+/// blocks of up to `block_len` frames, ending early at the curve's
+/// breakpoints, and at the start of each block the io ratio is slewed
+/// towards the curve's speed at the end of the block, so that the speed
+/// follows the curve piecewise linearly.
+pub struct Varispeed<C, In, Out = In>
+    where In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{
+    soxr: Soxr<In, Out>,
+    curve: C,
+    nominal_ratio: f64,
+    block_len: usize,
+    /// Current io ratio
+    ratio: f64,
+    /// Input frames consumed so far
+    position: usize,
+    /// Position at which the next ratio change is due
+    boundary: usize,
+}
+
+impl<C, In, Out> Varispeed<C, In, Out>
+    where C: RateCurve, In: FrameFormat, Out: FrameFormat<Channels = In::Channels>
+{
+    /// Drive `soxr`, which must have been created with variable rate
+    /// [`QualitySpec`](crate::params::QualitySpec), with `curve`
+    pub fn new(
+        soxr: Soxr<In, Out>,
+        input_rate: f64,
+        output_rate: f64,
+        curve: C,
+        block_len: usize,
+    ) -> Result<Self, Error> {
+        let mut varispeed = Varispeed {
+            soxr,
+            curve,
+            nominal_ratio: input_rate / output_rate,
+            block_len: block_len.max(1),
+            ratio: 0.0,
+            position: 0,
+            boundary: 0,
+        };
+
+        varispeed.ratio = varispeed.ratio_at(0);
+        varispeed.soxr.set_io_ratio(varispeed.ratio, 0)?;
+
+        Ok(varispeed)
+    }
+
+    pub fn soxr(&self) -> &Soxr<In, Out> {
+        &self.soxr
+    }
+
+    pub fn into_inner(self) -> Soxr<In, Out> {
+        self.soxr
+    }
+
+    /// Input frames consumed so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Current io ratio
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    fn ratio_at(&mut self, position: usize) -> f64 {
+        let speed = self.curve.speed_at(position as f64).max(MIN_SPEED);
+        self.nominal_ratio * speed
+    }
+
+    /// Process audio through the resampler, splitting input at block
+    /// boundaries and breakpoints to update the io ratio. Once finished, call `drain` until
+    /// it returns `0`.
+    pub fn process(&mut self, input: &[In::Frame], output: &mut [Out::Frame])
+        -> Result<Processed, Error>
+    {
+        let mut total = Processed {
+            input_frames: 0,
+            output_frames: 0,
+            clips: 0,
+        };
+
+        loop {
+            if self.position == self.boundary {
+                let mut next = self.position + self.block_len;
+
+                // breakpoints lie strictly after the current position, so
+                // the block is never empty
+                if let Some(breakpoint) = self.curve.next_breakpoint(self.position as f64) {
+                    next = next.min(libm::ceil(breakpoint) as usize);
+                }
+
+                let ratio = self.ratio_at(next);
+
+                // slew over the output frames this block should produce at
+                // the average of the old and new ratios
+                let block_len = (next - self.position) as f64;
+                let slew_len = 2.0 * block_len / (self.ratio + ratio);
+
+                self.soxr.set_io_ratio(ratio, slew_len as usize)?;
+                self.ratio = ratio;
+                self.boundary = next;
+            }
+
+            let remaining = &input[total.input_frames..];
+            let take = remaining.len().min(self.boundary - self.position);
+
+            let processed = self.soxr.process(
+                In::input(&remaining[..take]),
+                Out::output(&mut output[total.output_frames..]),
+            )?;
+
+            self.position += processed.input_frames;
+            total.input_frames += processed.input_frames;
+            total.output_frames += processed.output_frames;
+            total.clips += processed.clips;
+
+            let made_progress = processed.input_frames > 0 || processed.output_frames > 0;
+            let input_done = total.input_frames == input.len();
+
+            if !made_progress || total.output_frames == output.len() || input_done {
+                return Ok(total);
+            }
+        }
+    }
+
+    /// Indicate to the resampler that the input stream has finished, and
+    /// read remaining buffered data out of resampler
+    pub fn drain(&mut self, output: &mut [Out::Frame]) -> Result<usize, Error> {
+        self.soxr.drain(Out::output(output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::Mono;
+    use crate::Soxr;
+
+    use super::{Breakpoint, Breakpoints, RateCurve, Shape, Varispeed};
+
+    fn point(position: f64, speed: f64, shape: Shape) -> Breakpoint {
+        Breakpoint { position, speed, shape }
+    }
+
+    #[test]
+    fn linear() {
+        let points = [point(100.0, 1.0, Shape::Linear), point(200.0, 2.0, Shape::Linear)];
+        let mut curve = Breakpoints::new(&points);
+
+        assert_eq!(curve.speed_at(100.0), 1.0);
+        assert_eq!(curve.speed_at(150.0), 1.5);
+        assert_eq!(curve.speed_at(175.0), 1.75);
+    }
+
+    #[test]
+    fn exponential() {
+        let points = [point(0.0, 1.0, Shape::Exponential), point(100.0, 4.0, Shape::Linear)];
+        let mut curve = Breakpoints::new(&points);
+
+        assert!((curve.speed_at(50.0) - 2.0).abs() < 1e-12);
+        assert!((curve.speed_at(25.0) - libm::sqrt(2.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn outside_breakpoints() {
+        let points = [point(100.0, 1.0, Shape::Linear), point(200.0, 2.0, Shape::Linear)];
+        let mut curve = Breakpoints::new(&points);
+
+        assert_eq!(curve.speed_at(0.0), 1.0);
+        assert_eq!(curve.speed_at(250.0), 2.0);
+        // lookups need not be in increasing order
+        assert_eq!(curve.speed_at(150.0), 1.5);
+        assert_eq!(curve.speed_at(50.0), 1.0);
+    }
+
+    #[test]
+    fn next_breakpoint() {
+        let points = [point(100.0, 1.0, Shape::Linear), point(200.0, 2.0, Shape::Linear)];
+        let curve = Breakpoints::new(&points);
+
+        assert_eq!(curve.next_breakpoint(0.0), Some(100.0));
+        assert_eq!(curve.next_breakpoint(100.0), Some(200.0));
+        assert_eq!(curve.next_breakpoint(150.0), Some(200.0));
+        assert_eq!(curve.next_breakpoint(200.0), None);
+    }
+
+    #[test]
+    fn blocks_end_at_breakpoints() {
+        // tape stop to half speed between frames 1500 and 2000
+        let points = [
+            point(0.0, 1.0, Shape::Linear),
+            point(1500.0, 1.0, Shape::Linear),
+            point(2000.0, 0.5, Shape::Linear),
+        ];
+
+        let soxr = Soxr::<Mono<f32>>::variable_rate(48000.0, 48000.0).unwrap();
+        let curve = Breakpoints::new(&points);
+        let mut varispeed = Varispeed::new(soxr, 48000.0, 48000.0, curve, 1024).unwrap();
+
+        let mut output = [0.0f32; 4096];
+        let mut blocks = [(0, 0.0); 4];
+        let mut count = 0;
+
+        // feed one frame at a time to observe the start of each block
+        for _ in 0..3000 {
+            let processed = varispeed.process(&[0.0], &mut output).unwrap();
+            assert_eq!(processed.input_frames, 1);
+
+            if count == 0 || blocks[count - 1].0 != varispeed.boundary {
+                blocks[count] = (varispeed.boundary, varispeed.ratio());
+                count += 1;
+            }
+        }
+
+        assert_eq!(blocks[..count], [(1024, 1.0), (1500, 1.0), (2000, 0.5), (3024, 0.5)]);
+    }
+}